@@ -0,0 +1,49 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use proconio::source::once::OnceSource;
+use rand_pcg::Mcg128Xsl64;
+use simulated_annealing::{
+    parse_source, run_with_config, run_with_rng, RunConfig, SeedRng, WyRand, Xorshift64Star,
+};
+
+const ITERATIONS: u64 = 200_000;
+
+/// n = 200 のケースで mc を回す
+fn mc_n200(c: &mut Criterion) {
+    let input = parse_source(OnceSource::new(
+        include_str!("../tools/in/0522.txt").as_bytes(),
+    ));
+    let mut config = RunConfig::new(None, input.rects.len()).unwrap();
+    config.iterations = Some(ITERATIONS);
+    let mut group = c.benchmark_group("mc");
+    group.throughput(Throughput::Elements(ITERATIONS));
+    group.sample_size(20);
+    group.bench_function("n200", |b| b.iter(|| run_with_config(&input, &config)));
+    let json = r#"{"acceptance": {"type": "fast_metropolis"}}"#;
+    let mut config = RunConfig::new(Some(json.to_owned()), input.rects.len()).unwrap();
+    config.iterations = Some(ITERATIONS);
+    group.bench_function("n200_fast", |b| b.iter(|| run_with_config(&input, &config)));
+    group.finish();
+}
+
+/// 乱数生成器ごとの mc（n = 200）
+fn mc_rng<R: SeedRng + Send + 'static>(c: &mut Criterion, name: &str) {
+    let input = parse_source(OnceSource::new(
+        include_str!("../tools/in/0522.txt").as_bytes(),
+    ));
+    let mut config = RunConfig::new(None, input.rects.len()).unwrap();
+    config.iterations = Some(ITERATIONS);
+    let mut group = c.benchmark_group("mc_rng");
+    group.throughput(Throughput::Elements(ITERATIONS));
+    group.sample_size(20);
+    group.bench_function(name, |b| b.iter(|| run_with_rng::<R>(&input, &config)));
+    group.finish();
+}
+
+fn mc_rngs(c: &mut Criterion) {
+    mc_rng::<Mcg128Xsl64>(c, "pcg");
+    mc_rng::<Xorshift64Star>(c, "xorshift64star");
+    mc_rng::<WyRand>(c, "wyrand");
+}
+
+criterion_group!(benches, mc_n200, mc_rngs);
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+use criterion::{criterion_group, criterion_main, Criterion as Bench, Throughput};
+use rand::Rng;
+use rand_pcg::Mcg128Xsl64;
+use simulated_annealing::{Criterion, FastMetropolis, Metropolis};
+
+const N: usize = 1024;
+
+/// 悪化する提案 N 回の判定（温度は DEFAULT_PARAMS の途中くらい）
+fn bench_accept<C: Criterion>(c: &mut Bench, name: &str, mut criterion: C) {
+    let mut rng = Mcg128Xsl64::new(1);
+    let diffs = (0..N)
+        .map(|_| -rng.gen::<f64>().powi(3) * 0.05)
+        .collect::<Vec<_>>();
+    criterion.prepare(0.005, 0.0, 0.0);
+    let mut group = c.benchmark_group("accept");
+    group.throughput(Throughput::Elements(N as u64));
+    group.bench_function(name, |b| {
+        b.iter(|| {
+            diffs
+                .iter()
+                .filter(|&&d| criterion.accept(&mut rng, d, 0.0))
+                .count()
+        })
+    });
+    group.finish();
+}
+
+/// sweep と同じく、提案の前に引いた下限と比べるだけで棄却する判定
+fn bench_presample<C: Criterion>(c: &mut Bench, name: &str, mut criterion: C) {
+    let mut rng = Mcg128Xsl64::new(1);
+    let diffs = (0..N)
+        .map(|_| -rng.gen::<f64>().powi(3) * 0.05)
+        .collect::<Vec<_>>();
+    criterion.prepare(0.005, 0.0, 0.0);
+    assert!(
+        criterion.presample(&mut rng).is_some(),
+        "{} has no early-reject path",
+        name
+    );
+    let mut group = c.benchmark_group("accept");
+    group.throughput(Throughput::Elements(N as u64));
+    group.bench_function(name, |b| {
+        b.iter(|| {
+            diffs
+                .iter()
+                .filter(|&&d| {
+                    let bound = criterion.presample(&mut rng).unwrap();
+                    d >= 0.0 || d > bound
+                })
+                .count()
+        })
+    });
+    group.finish();
+}
+
+fn accept(c: &mut Bench) {
+    bench_accept(c, "metropolis", Metropolis::new(1.0));
+    bench_accept(c, "fast_metropolis", FastMetropolis::new(1.0));
+    bench_presample(c, "fast_metropolis_presample", FastMetropolis::new(1.0));
+}
+
+criterion_group!(benches, accept);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use proconio::source::once::OnceSource;
+use rand::Rng;
+use rand_pcg::Mcg128Xsl64;
+use simulated_annealing::{parse_source, run_with_config, QTree, Rect, RunConfig};
+
+/// n = 200 のケースを 200 万回焼きなました配置で、各長方形の辺を広げた細い部分の重なりを調べる
+/// mc の内側のループで QTree::intersect に渡すものと同じ形
+fn qtree_intersect(c: &mut Criterion) {
+    let input = parse_source(OnceSource::new(
+        include_str!("../tools/in/0522.txt").as_bytes(),
+    ));
+    let mut config = RunConfig::new(None, input.rects.len()).unwrap();
+    config.iterations = Some(2_000_000);
+    let (_, rects) = run_with_config(&input, &config);
+    let qtree = QTree::new(&rects);
+
+    let mut rng = Mcg128Xsl64::new(1);
+    let grows = (0..1024)
+        .filter_map(|_| {
+            let rect = &rects[rng.gen_range(0, rects.len())];
+            let d = rng.gen_range(1, 100);
+            match rng.gen_range(0, 4) {
+                0 => rect.grow_x1(-d),
+                1 => rect.grow_x2(d),
+                2 => rect.grow_y1(-d),
+                _ => rect.grow_y2(d),
+            }
+            .and_then(|new| rect.grow_rect(&new))
+        })
+        .collect::<Vec<Rect>>();
+
+    let mut group = c.benchmark_group("qtree");
+    group.throughput(Throughput::Elements(grows.len() as u64));
+    group.bench_function("intersect", |b| {
+        b.iter(|| {
+            grows
+                .iter()
+                .filter(|grow| qtree.intersect(grow, &rects))
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, qtree_intersect);
+criterion_main!(benches);
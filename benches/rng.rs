@@ -4,6 +4,7 @@ use rand::{
     Rng,
 };
 use rand_pcg::Mcg128Xsl64;
+use simulated_annealing::{SeedRng, WyRand, Xorshift64Star};
 
 fn uniform_01(c: &mut Criterion) {
     let mut rng = Mcg128Xsl64::new(1);
@@ -20,5 +21,24 @@ fn gen_f64(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, uniform_01, gen_f64);
+/// mc の内側で使う分だけ（f64 と、長方形の番号と移動量の Uniform）
+fn bench_rng<R: SeedRng>(c: &mut Criterion, name: &str) {
+    let mut group = c.benchmark_group(name);
+    let mut rng = R::seeded(1);
+    group.bench_function("next_u64", |b| b.iter(|| rng.next_u64()));
+    group.bench_function("gen_f64", |b| b.iter(|| rng.gen::<f64>()));
+    let index = Uniform::new(0, 200usize);
+    group.bench_function("uniform_index", |b| b.iter(|| index.sample(&mut rng)));
+    let d = Uniform::new_inclusive(-100i16, 100);
+    group.bench_function("uniform_i16", |b| b.iter(|| d.sample(&mut rng)));
+    group.finish();
+}
+
+fn rngs(c: &mut Criterion) {
+    bench_rng::<Mcg128Xsl64>(c, "pcg");
+    bench_rng::<Xorshift64Star>(c, "xorshift64star");
+    bench_rng::<WyRand>(c, "wyrand");
+}
+
+criterion_group!(benches, uniform_01, gen_f64, rngs);
 criterion_main!(benches);
@@ -1,4 +1,24 @@
+import re
+from pathlib import Path
+
+SRC = Path('simulated-annealing/src')
+
+
+def expand(path: Path) -> str:
+    out = []
+    for line in path.open():
+        m = re.match(r'^(\s*)(pub(?:\(crate\))? )?mod (\w+);$', line.rstrip('\n'))
+        if m is None:
+            out.append(line)
+            continue
+        indent, vis, name = m.groups()
+        out.append(f'{indent}{vis or ""}mod {name} {{\n')
+        out.append(expand(SRC / f'{name}.rs'))
+        out.append(f'{indent}}}\n')
+    return ''.join(out)
+
+
 print('mod simulated_annealing {')
-print(open('simulated-annealing/src/lib.rs').read())
+print(expand(SRC / 'lib.rs'))
 print('}')
 print(open('src/bin/local.rs').read())
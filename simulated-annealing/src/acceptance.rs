@@ -0,0 +1,344 @@
+use rand::{Rng, RngCore};
+use rand_distr::Exp1;
+#[cfg(feature = "learn")]
+use serde::{Deserialize, Serialize};
+
+/// 悪化する変更を受理するかの判定
+///
+/// スコアはすべて長方形のスコアの和（0〜n）で、温度は McParams の temp0, temp1 のスケジュール。
+pub trait Criterion {
+    /// 時刻 t の 2000 回の提案の前に呼ぶ
+    fn prepare(&mut self, temp: f64, current: f64, best: f64);
+    /// 全体のスコアが current のとき、1つの長方形のスコアが diff 変わる提案を受理するか
+    fn accept<R: RngCore>(&mut self, rng: &mut R, diff: f64, current: f64) -> bool;
+    /// 提案を作る前に受理の下限 b を引く
+    /// Some(b) なら diff >= 0 か diff > b のときだけ受理し、accept は呼ばない
+    /// 判定に使う乱数が diff によらず、判定で状態が変わらないものだけが Some を返す
+    #[inline]
+    fn presample<R: RngCore>(&mut self, _rng: &mut R) -> Option<f64> {
+        None
+    }
+}
+
+/// 受理判定の種類
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)
+)]
+pub enum Acceptance {
+    /// exp(diff / temp) の確率で受理する（焼きなまし）
+    Metropolis,
+    /// Metropolis と同じ確率で、exp を計算せずに判定する
+    /// 乱数の使い方が違うので、同じ seed でも Metropolis とは違う結果になる
+    FastMetropolis,
+    /// diff >= -temp なら受理する
+    Threshold,
+    /// 変更後のスコアが水位以上なら受理する
+    /// 水位は最初のスコアから始まり、1回の判定ごとに rain * n 上がる
+    GreatDeluge { rain: f64 },
+    /// 変更後のスコアが最良スコア - temp 以上なら受理する
+    RecordToRecord,
+    /// 変更後のスコアが length 回前の判定のときのスコア以上なら受理する
+    LateAcceptance { length: usize },
+}
+
+impl Acceptance {
+    pub fn is_metropolis(&self) -> bool {
+        *self == Acceptance::Metropolis
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            Acceptance::GreatDeluge { rain } if !(rain.is_finite() && rain >= 0.0) => Err(format!(
+                "great deluge rain must be non-negative (got {})",
+                rain
+            )),
+            Acceptance::LateAcceptance { length: 0 } => {
+                Err("late acceptance length must be positive".to_owned())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// n は長方形の数
+    pub(crate) fn criterion(&self, n: usize) -> Criteria {
+        match *self {
+            Acceptance::Metropolis => Criteria::Metropolis(Metropolis { beta: 0.0 }),
+            Acceptance::FastMetropolis => Criteria::FastMetropolis(FastMetropolis { beta: 0.0 }),
+            Acceptance::Threshold => Criteria::Threshold(Threshold { threshold: 0.0 }),
+            Acceptance::GreatDeluge { rain } => Criteria::GreatDeluge(GreatDeluge {
+                step: rain * n as f64,
+                level: 0.0,
+                started: false,
+            }),
+            Acceptance::RecordToRecord => Criteria::RecordToRecord(RecordToRecord { floor: 0.0 }),
+            Acceptance::LateAcceptance { length } => Criteria::LateAcceptance(LateAcceptance {
+                history: Vec::with_capacity(length),
+                length,
+                k: 0,
+            }),
+        }
+    }
+}
+
+/// 焼きなましの状態として持つ判定
+pub(crate) enum Criteria {
+    Metropolis(Metropolis),
+    FastMetropolis(FastMetropolis),
+    Threshold(Threshold),
+    GreatDeluge(GreatDeluge),
+    RecordToRecord(RecordToRecord),
+    LateAcceptance(LateAcceptance),
+}
+
+/// 判定のたびに種類で分岐するので、sweep では `sweep_criteria` で中身の型ごとに呼び分ける
+impl Criterion for Criteria {
+    fn prepare(&mut self, temp: f64, current: f64, best: f64) {
+        match self {
+            Criteria::Metropolis(c) => c.prepare(temp, current, best),
+            Criteria::FastMetropolis(c) => c.prepare(temp, current, best),
+            Criteria::Threshold(c) => c.prepare(temp, current, best),
+            Criteria::GreatDeluge(c) => c.prepare(temp, current, best),
+            Criteria::RecordToRecord(c) => c.prepare(temp, current, best),
+            Criteria::LateAcceptance(c) => c.prepare(temp, current, best),
+        }
+    }
+
+    fn accept<R: RngCore>(&mut self, rng: &mut R, diff: f64, current: f64) -> bool {
+        match self {
+            Criteria::Metropolis(c) => c.accept(rng, diff, current),
+            Criteria::FastMetropolis(c) => c.accept(rng, diff, current),
+            Criteria::Threshold(c) => c.accept(rng, diff, current),
+            Criteria::GreatDeluge(c) => c.accept(rng, diff, current),
+            Criteria::RecordToRecord(c) => c.accept(rng, diff, current),
+            Criteria::LateAcceptance(c) => c.accept(rng, diff, current),
+        }
+    }
+}
+
+pub struct Metropolis {
+    beta: f64,
+}
+
+impl Metropolis {
+    pub fn new(temp: f64) -> Metropolis {
+        Metropolis { beta: 1.0 / temp }
+    }
+}
+
+impl Criterion for Metropolis {
+    fn prepare(&mut self, temp: f64, _current: f64, _best: f64) {
+        self.beta = 1.0 / temp;
+    }
+
+    #[inline]
+    fn accept<R: RngCore>(&mut self, rng: &mut R, diff: f64, _current: f64) -> bool {
+        diff >= 0.0 || rng.gen::<f64>() < (diff * self.beta).exp()
+    }
+}
+
+/// Metropolis と同じ確率で受理する
+///
+/// u < exp(diff / temp) は E = -ln u（指数分布）を使うと diff > -E * temp と同じなので、
+/// 一様乱数と exp の代わりに ziggurat 法で E を引いて比べる（exp も ln もほとんど計算しない）。
+/// sweep では提案を作る前に `presample` で下限 -E * temp を引き、スコアの差が下限以下なら
+/// 点を含むかや重なりを調べずに棄却する。
+pub struct FastMetropolis {
+    beta: f64,
+}
+
+impl FastMetropolis {
+    pub fn new(temp: f64) -> FastMetropolis {
+        FastMetropolis { beta: 1.0 / temp }
+    }
+
+    /// 指数分布の e に対する受理の下限
+    #[inline]
+    fn bound(&self, e: f64) -> f64 {
+        -e / self.beta
+    }
+}
+
+impl Criterion for FastMetropolis {
+    fn prepare(&mut self, temp: f64, _current: f64, _best: f64) {
+        self.beta = 1.0 / temp;
+    }
+
+    #[inline]
+    fn accept<R: RngCore>(&mut self, rng: &mut R, diff: f64, _current: f64) -> bool {
+        if diff >= 0.0 {
+            return true;
+        }
+        let e = rng.sample::<f64, _>(Exp1);
+        diff > self.bound(e)
+    }
+
+    #[inline]
+    fn presample<R: RngCore>(&mut self, rng: &mut R) -> Option<f64> {
+        Some(self.bound(rng.sample::<f64, _>(Exp1)))
+    }
+}
+
+pub struct Threshold {
+    threshold: f64,
+}
+
+impl Criterion for Threshold {
+    fn prepare(&mut self, temp: f64, _current: f64, _best: f64) {
+        self.threshold = temp;
+    }
+
+    #[inline]
+    fn accept<R: RngCore>(&mut self, _rng: &mut R, diff: f64, _current: f64) -> bool {
+        diff >= -self.threshold
+    }
+}
+
+pub struct GreatDeluge {
+    /// 1回の判定で上がる水位（rain * n）
+    step: f64,
+    level: f64,
+    started: bool,
+}
+
+impl Criterion for GreatDeluge {
+    fn prepare(&mut self, _temp: f64, current: f64, _best: f64) {
+        if !self.started {
+            self.level = current;
+            self.started = true;
+        }
+    }
+
+    #[inline]
+    fn accept<R: RngCore>(&mut self, _rng: &mut R, diff: f64, current: f64) -> bool {
+        let ok = diff >= 0.0 || current + diff >= self.level;
+        self.level += self.step;
+        ok
+    }
+}
+
+pub struct RecordToRecord {
+    floor: f64,
+}
+
+impl Criterion for RecordToRecord {
+    fn prepare(&mut self, temp: f64, _current: f64, best: f64) {
+        self.floor = best - temp;
+    }
+
+    #[inline]
+    fn accept<R: RngCore>(&mut self, _rng: &mut R, diff: f64, current: f64) -> bool {
+        diff >= 0.0 || current + diff >= self.floor
+    }
+}
+
+pub struct LateAcceptance {
+    history: Vec<f64>,
+    length: usize,
+    k: usize,
+}
+
+impl Criterion for LateAcceptance {
+    fn prepare(&mut self, _temp: f64, current: f64, _best: f64) {
+        if self.history.is_empty() {
+            self.history = vec![current; self.length];
+        }
+    }
+
+    /// 履歴には判定した時点（変更前）のスコアを残す
+    #[inline]
+    fn accept<R: RngCore>(&mut self, _rng: &mut R, diff: f64, current: f64) -> bool {
+        let v = self.k % self.length;
+        self.k += 1;
+        let ok = diff >= 0.0 || current + diff >= self.history[v];
+        self.history[v] = current;
+        ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_pcg::Mcg128Xsl64;
+
+    use super::*;
+
+    #[test]
+    fn test_fast_metropolis() {
+        let mut rng = Mcg128Xsl64::new(1);
+        let n = 200_000;
+        for &x in [-0.1, -1.0, -3.0].iter() {
+            let mut a = Metropolis::new(0.01);
+            let mut b = FastMetropolis::new(0.01);
+            let count = |c: &mut dyn FnMut(&mut Mcg128Xsl64) -> bool, rng: &mut Mcg128Xsl64| {
+                (0..n).filter(|_| c(rng)).count() as f64 / n as f64
+            };
+            let p = count(&mut |rng| a.accept(rng, x * 0.01, 0.0), &mut rng);
+            let q = count(&mut |rng| b.accept(rng, x * 0.01, 0.0), &mut rng);
+            assert!((p - x.exp()).abs() < 0.005, "{} {}", p, x.exp());
+            assert!((q - x.exp()).abs() < 0.005, "{} {}", q, x.exp());
+        }
+    }
+
+    #[test]
+    fn test_fast_metropolis_bound() {
+        // 同じ一様乱数 u から E = -ln u とした下限で、Metropolis と同じ判定になる
+        let mut rng = Mcg128Xsl64::new(1);
+        let mut uniform = rng.clone();
+        for &temp in [0.001, 0.01, 0.1].iter() {
+            let mut a = Metropolis::new(temp);
+            let b = FastMetropolis::new(temp);
+            for _ in 0..10_000 {
+                let diff = -temp * 5.0 * (uniform.gen::<f64>() + 1e-9);
+                let _ = rng.gen::<f64>();
+                let u = uniform.gen::<f64>();
+                let bound = b.bound(-u.ln());
+                assert_eq!(
+                    a.accept(&mut rng, diff, 0.0),
+                    diff > bound,
+                    "{} {}",
+                    diff,
+                    u
+                );
+            }
+        }
+        let mut c = FastMetropolis::new(0.01);
+        assert!(c.presample(&mut rng).unwrap() < 0.0);
+        assert_eq!(Metropolis::new(0.01).presample(&mut rng), None);
+    }
+
+    #[test]
+    fn test_criteria() {
+        let mut rng = Mcg128Xsl64::new(1);
+
+        let mut c = Threshold { threshold: 0.0 };
+        c.prepare(0.1, 50.0, 60.0);
+        assert!(c.accept(&mut rng, -0.1, 50.0));
+        assert!(!c.accept(&mut rng, -0.2, 50.0));
+
+        let mut c = RecordToRecord { floor: 0.0 };
+        c.prepare(1.0, 50.0, 60.0);
+        assert!(!c.accept(&mut rng, -0.5, 50.0));
+        assert!(c.accept(&mut rng, -0.5, 59.5));
+
+        let mut c = match (Acceptance::GreatDeluge { rain: 0.01 }).criterion(100) {
+            Criteria::GreatDeluge(c) => c,
+            _ => unreachable!(),
+        };
+        c.prepare(1.0, 50.0, 50.0);
+        assert!(c.accept(&mut rng, -0.5, 50.5));
+        // 水位は 51 まで上がっている
+        assert!(!c.accept(&mut rng, -0.5, 51.4));
+
+        let mut c = match (Acceptance::LateAcceptance { length: 2 }).criterion(100) {
+            Criteria::LateAcceptance(c) => c,
+            _ => unreachable!(),
+        };
+        c.prepare(1.0, 50.0, 50.0);
+        assert!(c.accept(&mut rng, -0.5, 52.0));
+        assert!(c.accept(&mut rng, -0.5, 51.0));
+        // 2回前の判定のときのスコアは 52
+        assert!(!c.accept(&mut rng, -0.5, 52.0));
+    }
+}
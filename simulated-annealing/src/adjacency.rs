@@ -0,0 +1,181 @@
+use super::{QTree, Rect, L};
+
+/// 長方形 i の辺に接している長方形 j
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Contact {
+    pub j: usize,
+    /// i のどの辺か（0: x1, 1: x2, 2: y1, 3: y2）
+    pub side: usize,
+    /// 共有している区間（side が x なら y の、y なら x の範囲）
+    pub span: (i16, i16),
+}
+
+/// a のどの辺で b と接しているか
+/// 角だけで接するものは含めない
+fn contact(a: &Rect, b: &Rect) -> Option<(usize, (i16, i16))> {
+    let ys = (a.y1.max(b.y1), a.y2.min(b.y2));
+    let xs = (a.x1.max(b.x1), a.x2.min(b.x2));
+    if ys.0 < ys.1 {
+        if a.x1 == b.x2 {
+            return Some((0, ys));
+        }
+        if a.x2 == b.x1 {
+            return Some((1, ys));
+        }
+    }
+    if xs.0 < xs.1 {
+        if a.y1 == b.y2 {
+            return Some((2, xs));
+        }
+        if a.y2 == b.y1 {
+            return Some((3, xs));
+        }
+    }
+    None
+}
+
+/// 辺を共有する長方形の組
+///
+/// 長方形を1つ変えるたびに `update` を呼べば、全部作り直さずに前後で接するものだけを直して追従する。
+#[derive(Debug, Clone)]
+pub struct Adjacency {
+    contacts: Vec<Vec<Contact>>,
+}
+
+impl Adjacency {
+    pub fn new(rects: &[Rect]) -> Adjacency {
+        let mut contacts = vec![Vec::new(); rects.len()];
+        for (i, a) in rects.iter().enumerate() {
+            for (j, b) in rects.iter().enumerate() {
+                if i != j {
+                    if let Some((side, span)) = contact(a, b) {
+                        contacts[i].push(Contact { j, side, span });
+                    }
+                }
+            }
+        }
+        Adjacency { contacts }
+    }
+
+    /// rects[i] と qtree を変えたあとに呼ぶ
+    /// 前に接していたものから外し、新しい rects[i] を 1 広げた範囲と重なるものだけを調べる
+    pub fn update(&mut self, i: usize, rects: &[Rect], qtree: &QTree) {
+        for c in std::mem::take(&mut self.contacts[i]) {
+            self.contacts[c.j].retain(|d| d.j != i);
+        }
+        let a = &rects[i];
+        let around = Rect::new(
+            (a.x1 - 1).max(0),
+            (a.x2 + 1).min(L),
+            (a.y1 - 1).max(0),
+            (a.y2 + 1).min(L),
+        );
+        let contacts = &mut self.contacts;
+        qtree.for_each_intersect(&around, rects, |j| {
+            if i == j {
+                return;
+            }
+            if let Some((side, span)) = contact(a, &rects[j]) {
+                contacts[i].push(Contact { j, side, span });
+                contacts[j].push(Contact {
+                    j: i,
+                    side: side ^ 1,
+                    span,
+                });
+            }
+        });
+    }
+
+    /// i に接している長方形
+    pub fn neighbors(&self, i: usize) -> &[Contact] {
+        &self.contacts[i]
+    }
+
+    /// 接している組を i < j で1回ずつ
+    pub fn pairs(&self) -> impl Iterator<Item = (usize, &Contact)> {
+        self.contacts
+            .iter()
+            .enumerate()
+            .flat_map(|(i, cs)| cs.iter().filter(move |c| i < c.j).map(move |c| (i, c)))
+    }
+
+    /// rects[i] の各辺が盤面の端か他の長方形に接しているか
+    /// 接している辺はそのまま外に広げられない
+    pub fn blocked(&self, i: usize, rects: &[Rect]) -> [bool; 4] {
+        let rect = &rects[i];
+        let mut blocked = [rect.x1 == 0, rect.x2 == L, rect.y1 == 0, rect.y2 == L];
+        for c in self.contacts[i].iter() {
+            blocked[c.side] = true;
+        }
+        blocked
+    }
+
+    /// 4辺とも blocked な長方形の数
+    pub fn boxed_in(&self, rects: &[Rect]) -> usize {
+        (0..rects.len())
+            .filter(|&i| self.blocked(i, rects).iter().all(|&b| b))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proconio::source::once::OnceSource;
+    use rand_pcg::Mcg128Xsl64;
+
+    use super::super::{parse_source, Annealer, DEFAULT_PARAMS};
+    use super::*;
+
+    #[test]
+    fn test_contact() {
+        // a の右に b、上に c、角だけ d
+        let a = Rect::new(0, 10, 0, 10);
+        let b = Rect::new(10, 20, 5, 30);
+        let c = Rect::new(2, 4, 10, 11);
+        let d = Rect::new(10, 11, 10, 11);
+        let rects = [a, b, c, d];
+        let adjacency = Adjacency::new(&rects);
+        assert_eq!(
+            adjacency.neighbors(0),
+            &[
+                Contact {
+                    j: 1,
+                    side: 1,
+                    span: (5, 10)
+                },
+                Contact {
+                    j: 2,
+                    side: 3,
+                    span: (2, 4)
+                },
+            ]
+        );
+        assert_eq!(adjacency.pairs().count(), 2);
+        // a は x1, y1 が盤面の端で、x2 に b、y2 に c
+        assert_eq!(adjacency.blocked(0, &rects), [true; 4]);
+        assert_eq!(adjacency.boxed_in(&rects), 1);
+    }
+
+    #[test]
+    fn test_adjacency_update() {
+        let input = parse_source(OnceSource::new(
+            include_str!("../../tools/in/0000.txt").as_bytes(),
+        ));
+        let mut rng = Mcg128Xsl64::new(1);
+        let mut annealer = Annealer::new(&DEFAULT_PARAMS, &input);
+        annealer.state.track_adjacency();
+        for k in 0..100 {
+            annealer.step(&mut rng, &DEFAULT_PARAMS, &input, k as f64 / 100.0);
+        }
+        let state = &annealer.state;
+        let expected = Adjacency::new(&state.rects);
+        let adjacency = state.adjacency.as_ref().unwrap();
+        for i in 0..state.rects.len() {
+            let mut actual = adjacency.neighbors(i).to_vec();
+            actual.sort();
+            assert_eq!(actual, expected.neighbors(i));
+        }
+        assert!(adjacency.pairs().count() > 0);
+        assert_eq!(annealer.stats.boxed_in.len(), 100);
+    }
+}
@@ -0,0 +1,116 @@
+use super::{Input, Rect, L};
+
+/// 入力だけから分かるスコアの上界
+#[derive(Debug, Clone)]
+pub struct Bound {
+    /// 長方形ごとの上界（0〜1）
+    pub scores: Vec<f64>,
+    /// 全体の上界（0〜1、scores の平均）
+    pub score: f64,
+}
+
+/// 点 i を含み、他の点を含まない長方形の最大の面積
+///
+/// 他の長方形は自分の点を含むので、i の長方形は他の点を含めない。
+/// 下端 y1 を決めると、上端を点の y の順に伸ばしながら左右の端は決まるので O(n²)。
+fn max_area(points: &[(i16, i16)], i: usize) -> i32 {
+    let (px, py) = points[i];
+    let mut above = points
+        .iter()
+        .enumerate()
+        .filter(|&(j, &(_, y))| j != i && y > py)
+        .map(|(_, &p)| p)
+        .collect::<Vec<_>>();
+    above.sort_by_key(|&(_, y)| y);
+    let mut lows = vec![0];
+    lows.extend(
+        points
+            .iter()
+            .enumerate()
+            .filter(|&(j, &(_, y))| j != i && y < py)
+            .map(|(_, &(_, y))| y + 1),
+    );
+
+    // 点 (x, _) を含めないように x の範囲を狭める。px と同じ列なら狭められない
+    let narrow = |(x1, x2): (i16, i16), x: i16| {
+        if x < px {
+            Some((x1.max(x + 1), x2))
+        } else if px < x {
+            Some((x1, x2.min(x)))
+        } else {
+            None
+        }
+    };
+    let mut best = 0;
+    for &y1 in lows.iter() {
+        // y1..=py にある点で左右を決める
+        let mut xs = Some((0, L));
+        for (j, &(x, y)) in points.iter().enumerate() {
+            if j != i && y1 <= y && y <= py {
+                xs = xs.and_then(|xs| narrow(xs, x));
+            }
+        }
+        for &(x, y) in above.iter() {
+            let (x1, x2) = match xs {
+                Some(xs) => xs,
+                None => break,
+            };
+            // 次の点の手前まで伸ばす
+            best = best.max(Rect::new(x1, x2, y1, y).size());
+            xs = narrow((x1, x2), x);
+        }
+        if let Some((x1, x2)) = xs {
+            best = best.max(Rect::new(x1, x2, y1, L).size());
+        }
+    }
+    best
+}
+
+/// スコアの上界
+///
+/// 長方形ごとに、点を含み他の点を含まない範囲で面積 r に届かなければその分だけ下がる。
+/// 長方形どうしの取り合いは見ないので、届くものは 1 として数える。
+pub fn upper_bound(input: &Input) -> Bound {
+    let scores = input
+        .sizes
+        .iter()
+        .enumerate()
+        .map(|(i, &r)| {
+            let s = max_area(&input.points, i).min(r) as f64 / r as f64;
+            1.0 - (1.0 - s) * (1.0 - s)
+        })
+        .collect::<Vec<_>>();
+    let score = scores.iter().sum::<f64>() / scores.len() as f64;
+    Bound { scores, score }
+}
+
+#[cfg(test)]
+mod tests {
+    use proconio::source::once::OnceSource;
+
+    use super::super::parse_source;
+    use super::*;
+
+    #[test]
+    fn test_max_area() {
+        // 左隣の点で x は 11 から。右上の点は y を 60 までにして避けるほうが広い
+        let points = vec![(15, 50), (10, 50), (20, 60)];
+        assert_eq!(max_area(&points, 0), (L as i32 - 11) * 60);
+        // 同じ列に他の点があると、y で避けるしかない
+        let points = vec![(15, 50), (15, 60)];
+        assert_eq!(max_area(&points, 0), L as i32 * 60);
+        // 点が1つなら盤面全体
+        assert_eq!(max_area(&[(0, 0)], 0), L as i32 * L as i32);
+
+        let input = parse_source(OnceSource::new(
+            include_str!("../../tools/in/0000.txt").as_bytes(),
+        ));
+        let bound = upper_bound(&input);
+        assert!(0.0 < bound.score && bound.score <= 1.0);
+        for (i, &s) in bound.scores.iter().enumerate() {
+            // 1x1 は必ず置ける
+            let unit = Rect::new(0, 1, 0, 1).score(input.sizes[i]);
+            assert!(unit <= s && s <= 1.0);
+        }
+    }
+}
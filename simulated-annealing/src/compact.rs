@@ -0,0 +1,148 @@
+#[cfg(feature = "learn")]
+use serde::{Deserialize, Serialize};
+
+use super::{Rect, BOARD};
+
+/// compact で長方形を寄せる向き
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Toward {
+    /// 中心が自分の点に近づくように
+    Points,
+    /// 座標の小さいほう（左、下）
+    Low,
+    /// 座標の大きいほう（右、上）
+    High,
+}
+
+/// x と y を入れ替える
+fn transpose(rect: &Rect) -> Rect {
+    Rect::new(rect.y1, rect.y2, rect.x1, rect.x2)
+}
+
+/// bounds の中で x 方向にだけ寄せる。何か動かしたら true
+fn compact_x(rects: &mut [Rect], points: &[(i16, i16)], toward: Toward, bounds: &Rect) -> bool {
+    // 動かしたい幅（負なら左）
+    let want = |rect: &Rect, x: i16| match toward {
+        Toward::Points => (2 * x + 1 - rect.x1 - rect.x2) / 2,
+        Toward::Low => bounds.x1 - rect.x1,
+        Toward::High => bounds.x2 - rect.x2,
+    };
+    // 行く手を塞ぐ長方形が先に動くように、左へは左から、右へは右から
+    let mut left = (0..rects.len())
+        .filter(|&i| want(&rects[i], points[i].0) < 0)
+        .collect::<Vec<_>>();
+    left.sort_by_key(|&i| rects[i].x1);
+    let mut right = (0..rects.len())
+        .filter(|&i| want(&rects[i], points[i].0) > 0)
+        .collect::<Vec<_>>();
+    right.sort_by_key(|&i| std::cmp::Reverse(rects[i].x2));
+
+    let mut moved = false;
+    for i in left {
+        let rect = &rects[i];
+        // 点を含んだまま、盤面の端か y が重なる長方形に当たるまで
+        let mut d = (-want(rect, points[i].0))
+            .min(rect.x2 - points[i].0 - 1)
+            .min(rect.x1 - bounds.x1);
+        for other in rects.iter() {
+            if other.x2 <= rect.x1 && other.y1.max(rect.y1) < other.y2.min(rect.y2) {
+                d = d.min(rect.x1 - other.x2);
+            }
+        }
+        if d > 0 {
+            rects[i] = rects[i].slide_x_within(-d, bounds).unwrap();
+            moved = true;
+        }
+    }
+    for i in right {
+        let rect = &rects[i];
+        let mut d = want(rect, points[i].0)
+            .min(points[i].0 - rect.x1)
+            .min(bounds.x2 - rect.x2);
+        for other in rects.iter() {
+            if rect.x2 <= other.x1 && other.y1.max(rect.y1) < other.y2.min(rect.y2) {
+                d = d.min(other.x1 - rect.x2);
+            }
+        }
+        if d > 0 {
+            rects[i] = rects[i].slide_x_within(d, bounds).unwrap();
+            moved = true;
+        }
+    }
+    moved
+}
+
+/// 重ならない rects を x、y の順に toward へ寄せられるだけ寄せる（VLSI の compaction）
+///
+/// 大きさは変えないのでスコアは変わらず、点は含んだまま。
+/// 隙間が片側にまとまるので、そのあとの焼きなましで小さい長方形が広がる余地になる。
+/// 動いた長方形の数を返す
+pub fn compact(rects: &mut [Rect], points: &[(i16, i16)], toward: Toward) -> usize {
+    compact_within(rects, points, toward, &BOARD)
+}
+
+/// bounds の中にある rects を、bounds の中で compact する
+pub(crate) fn compact_within(
+    rects: &mut [Rect],
+    points: &[(i16, i16)],
+    toward: Toward,
+    bounds: &Rect,
+) -> usize {
+    let before = rects.to_vec();
+    // Points は他が動くと先へ進めることがあるので、動かなくなるまで繰り返す
+    while compact_x(rects, points, toward, bounds) && toward == Toward::Points {}
+    let mut transposed = rects.iter().map(transpose).collect::<Vec<_>>();
+    let points = points.iter().map(|&(x, y)| (y, x)).collect::<Vec<_>>();
+    let bounds = transpose(bounds);
+    while compact_x(&mut transposed, &points, toward, &bounds) && toward == Toward::Points {}
+    for (rect, t) in rects.iter_mut().zip(transposed.iter()) {
+        *rect = transpose(t);
+    }
+    rects
+        .iter()
+        .zip(before.iter())
+        .filter(|(a, b)| (a.x1, a.y1) != (b.x1, b.y1))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use proconio::source::once::OnceSource;
+
+    use super::super::{calc_score, guillotine, intersect, parse_source};
+    use super::*;
+
+    #[test]
+    fn test_compact() {
+        // 右の長方形は x では左の長方形に当たり、y では点で止まる
+        let points = vec![(5, 10), (20, 10)];
+        let mut rects = vec![Rect::new(5, 15, 0, 20), Rect::new(20, 40, 5, 15)];
+        assert_eq!(compact(&mut rects, &points, Toward::Low), 2);
+        assert_eq!((rects[0].x1, rects[0].y1), (0, 0));
+        assert_eq!((rects[1].x1, rects[1].y1), (10, 1));
+
+        // 点が中心に来る
+        let mut rects = vec![Rect::new(0, 20, 0, 20)];
+        compact(&mut rects, &[(100, 5)], Toward::Points);
+        assert_eq!((rects[0].x1, rects[0].x2, rects[0].y1), (90, 110, 0));
+
+        let input = parse_source(OnceSource::new(
+            include_str!("../../tools/in/0000.txt").as_bytes(),
+        ));
+        for toward in [Toward::Points, Toward::Low, Toward::High] {
+            let mut rects = guillotine(&input.points, &input.sizes);
+            let before = calc_score(&rects, &input.sizes).0;
+            assert!(compact(&mut rects, &input.points, toward) > 0);
+            assert_eq!(calc_score(&rects, &input.sizes).0, before);
+            for (i, rect) in rects.iter().enumerate() {
+                assert!(rect.contain(input.points[i].0, input.points[i].1));
+                assert!(!intersect(rect, &rects[i + 1..]));
+            }
+        }
+    }
+}
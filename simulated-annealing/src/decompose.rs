@@ -0,0 +1,251 @@
+use std::time::Instant;
+
+#[cfg(feature = "learn")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    calc_score,
+    init::cut,
+    params::{McParams, ParamsError},
+    progress, Annealer, Budget, Input, Rect, SeedRng, Stats, BOARD,
+};
+
+/// 盤面を分けて解く設定
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Decompose {
+    /// 分ける領域の数（点が足りなければそれより少ない）
+    pub regions: usize,
+    /// 合わせたあとに盤面全体で焼きなます予算の割合（t = 1 - polish から 1 まで）
+    pub polish: f64,
+}
+
+impl Decompose {
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        if self.regions == 0 {
+            return Err(ParamsError::Invalid(
+                "decompose regions must be positive".to_owned(),
+            ));
+        }
+        if !(0.0..1.0).contains(&self.polish) {
+            return Err(ParamsError::Invalid(format!(
+                "decompose polish must be in [0, 1) (got {})",
+                self.polish
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 盤面の一部と、その中にある点
+struct Region {
+    bounds: Rect,
+    ids: Vec<usize>,
+}
+
+/// 点の多い領域から順に、guillotine と同じ切り方で count 個まで分ける
+fn split(input: &Input, count: usize) -> Vec<Region> {
+    let mut regions = vec![Region {
+        bounds: BOARD,
+        ids: (0..input.points.len()).collect(),
+    }];
+    while regions.len() < count {
+        let k = (0..regions.len())
+            .max_by_key(|&k| regions[k].ids.len())
+            .unwrap();
+        if regions[k].ids.len() < 2 {
+            break;
+        }
+        let mut region = regions.swap_remove(k);
+        let (first, second, m) = cut(&region.bounds, &mut region.ids, &input.points, &input.sizes);
+        let rest = region.ids.split_off(m);
+        regions.push(Region {
+            bounds: first,
+            ids: region.ids,
+        });
+        regions.push(Region {
+            bounds: second,
+            ids: rest,
+        });
+    }
+    regions
+}
+
+/// region の中の点だけの入力
+/// 始める配置は region で切り取る（点は region の中にあるので空にはならない）
+fn sub_input(input: &Input, region: &Region) -> Input {
+    let b = &region.bounds;
+    Input {
+        rects: region
+            .ids
+            .iter()
+            .map(|&i| {
+                let r = &input.rects[i];
+                Rect::new(
+                    r.x1.max(b.x1),
+                    r.x2.min(b.x2),
+                    r.y1.max(b.y1),
+                    r.y2.min(b.y2),
+                )
+            })
+            .collect(),
+        points: region.ids.iter().map(|&i| input.points[i]).collect(),
+        sizes: region.ids.iter().map(|&i| input.sizes[i]).collect(),
+    }
+}
+
+/// bounds の中だけで sub を焼きなます
+fn solve<R: SeedRng>(
+    mut rng: R,
+    params: &McParams,
+    sub: &Input,
+    bounds: Rect,
+    budget: Budget,
+    now: Instant,
+) -> (f64, Vec<Rect>, Stats) {
+    let mut annealer = Annealer::new(params, sub);
+    annealer.state.bounds = bounds;
+    while let Some(t) = progress(budget, now, annealer.stats.all) {
+        annealer.step(&mut rng, params, sub, t);
+    }
+    annealer.finish()
+}
+
+/// 盤面を分けて並列に解く
+///
+/// 点の集合を分ける線で盤面を regions 個の領域に分け、それぞれを別のスレッドで
+/// 領域の外に出ないように焼きなます。合わせた配置から、予算の polish の割合だけ
+/// 盤面全体で低い温度から焼きなまして境界の隙間を埋める。
+/// budget は全体の量で、提案回数なら領域ごとに点の数に比例して分ける（時間なら並列に使う）。
+/// Stats は全焼きなましの合計で、regions に領域ごとの (点の数, 最良スコア) が入る。
+pub fn decompose<R: SeedRng + Send + 'static>(
+    seed: u64,
+    params: &McParams,
+    input: &Input,
+    budget: Budget,
+    config: &Decompose,
+) -> (f64, Vec<Rect>, Stats) {
+    let now = Instant::now();
+    let n = input.points.len();
+    let regions = split(input, config.regions);
+    let local = |k: usize| match budget {
+        Budget::Iterations(count) => {
+            let share = count as f64 * (1.0 - config.polish) * regions[k].ids.len() as f64;
+            Budget::Iterations((share / n as f64) as u64)
+        }
+        Budget::Time(limit) => Budget::Time(limit.mul_f64(1.0 - config.polish)),
+    };
+    // スレッドにはそれぞれの領域の入力を作って渡す
+    let handles = (0..regions.len())
+        .map(|k| {
+            let rng = R::seeded(seed as u128 ^ ((k as u128) << 64));
+            let (sub, bounds, budget) = (
+                sub_input(input, &regions[k]),
+                regions[k].bounds.clone(),
+                local(k),
+            );
+            let params = params.clone();
+            std::thread::spawn(move || solve(rng, &params, &sub, bounds, budget, now))
+        })
+        .collect::<Vec<_>>();
+    let results = handles
+        .into_iter()
+        .map(|h| h.join().unwrap())
+        .collect::<Vec<_>>();
+
+    let mut stats = Stats::default();
+    let mut rects = input.rects.clone();
+    for (region, (score, best, s)) in regions.iter().zip(results) {
+        for (&i, rect) in region.ids.iter().zip(best) {
+            rects[i] = rect;
+        }
+        stats.all += s.all;
+        stats.tried += s.tried;
+        stats.valid += s.valid;
+        stats.ac += s.ac;
+        stats.regions.push((region.ids.len(), score));
+    }
+    if config.polish == 0.0 {
+        let score = calc_score(&rects, &input.sizes).0 / n as f64;
+        return (score, rects, stats);
+    }
+
+    // 残りの予算で、t = 1 - polish から盤面全体を焼きなます
+    let (budget, now) = match budget {
+        Budget::Iterations(count) => (Budget::Iterations(count - stats.all.min(count)), now),
+        Budget::Time(limit) => (
+            Budget::Time(limit.checked_sub(now.elapsed()).unwrap_or_default()),
+            Instant::now(),
+        ),
+    };
+    let mut rng = R::seeded(seed as u128 ^ ((regions.len() as u128) << 64));
+    let t0 = 1.0 - config.polish;
+    let mut annealer = Annealer::from_rects(params, rects, &input.sizes);
+    while let Some(t) = progress(budget, now, annealer.stats.all) {
+        annealer.step(&mut rng, params, input, t0 + (1.0 - t0) * t);
+    }
+    let (score, best, s) = annealer.finish();
+    stats.all += s.all;
+    stats.tried += s.tried;
+    stats.valid += s.valid;
+    stats.ac += s.ac;
+    (score, best, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use proconio::source::once::OnceSource;
+    use rand_pcg::Mcg128Xsl64;
+
+    use super::super::{intersect, parse_source, DEFAULT_PARAMS};
+    use super::*;
+
+    #[test]
+    fn test_decompose() {
+        let input = parse_source(OnceSource::new(
+            include_str!("../../tools/in/0000.txt").as_bytes(),
+        ));
+        let n = input.points.len();
+        let regions = split(&input, 4);
+        assert_eq!(regions.len(), 4);
+        assert_eq!(regions.iter().map(|r| r.ids.len()).sum::<usize>(), n);
+
+        let budget = Budget::Iterations(200_000);
+        let config = Decompose {
+            regions: 4,
+            polish: 0.0,
+        };
+        assert!(config.validate().is_ok());
+        let (score, best, stats) =
+            decompose::<Mcg128Xsl64>(1, &DEFAULT_PARAMS, &input, budget, &config);
+        assert_eq!(stats.regions.len(), 4);
+        // 領域の外には出ない
+        for region in regions.iter() {
+            let b = &region.bounds;
+            for &i in region.ids.iter() {
+                let rect = &best[i];
+                assert!(b.x1 <= rect.x1 && rect.x2 <= b.x2 && b.y1 <= rect.y1 && rect.y2 <= b.y2);
+            }
+        }
+        assert!((calc_score(&best, &input.sizes).0 / n as f64 - score).abs() < 1e-9);
+
+        let config = Decompose {
+            regions: 4,
+            polish: 0.2,
+        };
+        let (polished, best, stats) =
+            decompose::<Mcg128Xsl64>(1, &DEFAULT_PARAMS, &input, budget, &config);
+        assert!(stats.all <= 200_000 + 5 * 2000);
+        for (i, rect) in best.iter().enumerate() {
+            assert!(rect.contain(input.points[i].0, input.points[i].1));
+            assert!(!intersect(rect, &best[i + 1..]));
+        }
+        // 提案回数で打ち切れば再現する
+        let (again, _, _) = decompose::<Mcg128Xsl64>(1, &DEFAULT_PARAMS, &input, budget, &config);
+        assert_eq!(polished, again);
+    }
+}
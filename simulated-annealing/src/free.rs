@@ -0,0 +1,162 @@
+use super::{Rect, L};
+
+/// 盤面のうち長方形に覆われていない部分
+#[derive(Debug, Clone)]
+pub struct FreeSpace {
+    /// 極大な空き長方形（どの向きにも広げられない）。互いに重なりうる
+    pub rects: Vec<Rect>,
+    /// 空いている面積
+    pub area: i32,
+}
+
+impl FreeSpace {
+    /// 盤面に対する空いている割合
+    pub fn ratio(&self) -> f64 {
+        self.area as f64 / (L as f64 * L as f64)
+    }
+}
+
+/// 重ならない rects の補集合を極大な空き長方形に分ける
+///
+/// 辺の座標で圧縮した格子の上で、行ごとに下へ空きが続く高さのヒストグラムをスタックで走査し、
+/// 左右と下に広げられないものから上にも広げられないものを残す。
+/// 格子の大きさを (2n + 2)² として、その数の手間で済む。
+pub fn free_space(rects: &[Rect]) -> FreeSpace {
+    let compress = |mut v: Vec<i16>| {
+        v.push(0);
+        v.push(L);
+        v.sort_unstable();
+        v.dedup();
+        v
+    };
+    let xs = compress(
+        rects
+            .iter()
+            .map(|r| r.x1)
+            .chain(rects.iter().map(|r| r.x2))
+            .collect(),
+    );
+    let ys = compress(
+        rects
+            .iter()
+            .map(|r| r.y1)
+            .chain(rects.iter().map(|r| r.y2))
+            .collect(),
+    );
+    let (w, h) = (xs.len() - 1, ys.len() - 1);
+
+    // 2次元の imos で各マスを覆う長方形の数を数える
+    let mut cover = vec![vec![0i32; w + 1]; h + 1];
+    for r in rects {
+        let (c1, c2) = (
+            xs.binary_search(&r.x1).unwrap(),
+            xs.binary_search(&r.x2).unwrap(),
+        );
+        let (r1, r2) = (
+            ys.binary_search(&r.y1).unwrap(),
+            ys.binary_search(&r.y2).unwrap(),
+        );
+        cover[r1][c1] += 1;
+        cover[r1][c2] -= 1;
+        cover[r2][c1] -= 1;
+        cover[r2][c2] += 1;
+    }
+    for row in cover.iter_mut() {
+        for c in 1..=w {
+            row[c] += row[c - 1];
+        }
+    }
+    for r in 1..=h {
+        let (done, rest) = cover.split_at_mut(r);
+        for (v, below) in rest[0].iter_mut().zip(done[r - 1].iter()) {
+            *v += below;
+        }
+    }
+    // used[r][c]: r 行目の c 列より左の埋まっているマスの数
+    let used = cover
+        .iter()
+        .take(h)
+        .map(|row| {
+            let mut acc = vec![0; w + 1];
+            for c in 0..w {
+                acc[c + 1] = acc[c] + (row[c] > 0) as i32;
+            }
+            acc
+        })
+        .collect::<Vec<_>>();
+
+    let mut area = 0;
+    let mut found = Vec::new();
+    let mut heights = vec![0; w + 1];
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    for r in 0..h {
+        for c in 0..w {
+            if cover[r][c] > 0 {
+                heights[c] = 0;
+            } else {
+                heights[c] += 1;
+                area += (xs[c + 1] - xs[c]) as i32 * (ys[r + 1] - ys[r]) as i32;
+            }
+        }
+        // heights[w] = 0 が番兵
+        for c in 0..=w {
+            let mut start = c;
+            while let Some(&(s, height)) = stack.last() {
+                if height <= heights[c] {
+                    break;
+                }
+                stack.pop();
+                // 列 s..c、行 r + 1 - height..=r は左右と下に広げられない
+                let top = r + 1 == h || used[r + 1][c] - used[r + 1][s] > 0;
+                if top {
+                    found.push(Rect::new(xs[s], xs[c], ys[r + 1 - height], ys[r + 1]));
+                }
+                start = s;
+            }
+            if heights[c] > 0
+                && stack
+                    .last()
+                    .map_or(true, |&(_, height)| height < heights[c])
+            {
+                stack.push((start, heights[c]));
+            }
+        }
+    }
+    FreeSpace { rects: found, area }
+}
+
+#[cfg(test)]
+mod tests {
+    use proconio::source::once::OnceSource;
+
+    use super::super::{guillotine, intersect, parse_source};
+    use super::*;
+
+    #[test]
+    fn test_free_space() {
+        // 盤面の真ん中に1つだけ
+        let m = L / 2;
+        let free = free_space(&[Rect::new(m - 1, m + 1, m - 1, m + 1)]);
+        assert_eq!(free.area, L as i32 * L as i32 - 4);
+        assert_eq!(free.rects.len(), 4);
+
+        let input = parse_source(OnceSource::new(
+            include_str!("../../tools/in/0000.txt").as_bytes(),
+        ));
+        let rects = guillotine(&input.points, &input.sizes);
+        let used = rects.iter().map(|r| r.size()).sum::<i32>();
+        let free = free_space(&rects);
+        assert_eq!(free.area, L as i32 * L as i32 - used);
+        assert!(!free.rects.is_empty());
+        for (k, e) in free.rects.iter().enumerate() {
+            assert!(!intersect(e, &rects));
+            assert!(free.rects[..k]
+                .iter()
+                .all(|f| (f.x1, f.x2, f.y1, f.y2) != (e.x1, e.x2, e.y1, e.y2)));
+            // どの向きに 1 広げても盤面から出るか他と重なる
+            for grown in [e.grow_x1(-1), e.grow_x2(1), e.grow_y1(-1), e.grow_y2(1)] {
+                assert!(grown.map_or(true, |g| intersect(&g, &rects)));
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+#[cfg(feature = "learn")]
+use serde::{Deserialize, Serialize};
+
+use super::{Input, Rect, L};
+
+/// mc を始める配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Init {
+    /// 各点の 1x1（parse_source のまま）
+    Unit,
+    /// guillotine で作った配置
+    Guillotine,
+}
+
+impl Default for Init {
+    fn default() -> Init {
+        Init::Unit
+    }
+}
+
+impl Init {
+    pub fn rects(self, input: &Input) -> Vec<Rect> {
+        match self {
+            Init::Unit => input.rects.clone(),
+            Init::Guillotine => guillotine(&input.points, &input.sizes),
+        }
+    }
+}
+
+/// 盤面を点の集合で再帰的に2分割して作る配置
+///
+/// 各段では、分割した両側の面積の比が r の和の比に近くなる切り方を x, y 両方向から選ぶ。
+/// 点が1つになった領域には、その点を含み面積が r 以下でなるべく正方形に近い長方形を置く。
+/// 領域が重ならないので、できる配置は常に正しい。
+pub fn guillotine(points: &[(i16, i16)], sizes: &[i32]) -> Vec<Rect> {
+    let mut rects = vec![Rect::new(0, 1, 0, 1); points.len()];
+    let mut ids = (0..points.len()).collect::<Vec<_>>();
+    split(&Rect::new(0, L, 0, L), &mut ids, points, sizes, &mut rects);
+    rects
+}
+
+fn split(
+    region: &Rect,
+    ids: &mut [usize],
+    points: &[(i16, i16)],
+    sizes: &[i32],
+    rects: &mut [Rect],
+) {
+    if ids.len() == 1 {
+        let i = ids[0];
+        rects[i] = fit(region, points[i], sizes[i]);
+        return;
+    }
+    let (first, second, k) = cut(region, ids, points, sizes);
+    let (left, right) = ids.split_at_mut(k);
+    split(&first, left, points, sizes, rects);
+    split(&second, right, points, sizes, rects);
+}
+
+/// 2つ以上の点がある region を、両側の面積の比が r の和の比に近くなるように2つに切る
+/// ids は並べ替えられ、先頭の k 個が1つ目の領域の点になる
+pub(crate) fn cut(
+    region: &Rect,
+    ids: &mut [usize],
+    points: &[(i16, i16)],
+    sizes: &[i32],
+) -> (Rect, Rect, usize) {
+    let total = ids.iter().map(|&i| sizes[i] as i64).sum::<i64>() as f64;
+    let width = region.x2 - region.x1;
+    let height = region.y2 - region.y1;
+    // (コスト, x で切るか, 左側の数, 切る座標)
+    let mut best: Option<(f64, bool, usize, i16)> = None;
+    for &vertical in [true, false].iter() {
+        let coord = |i: usize| if vertical { points[i].0 } else { points[i].1 };
+        let (lo, hi) = if vertical {
+            (region.x1, region.x2)
+        } else {
+            (region.y1, region.y2)
+        };
+        // 短い辺に沿って切ると細長くなるので割高にする
+        let penalty = if (width >= height) == vertical {
+            1.0
+        } else {
+            2.0
+        };
+        ids.sort_by_key(|&i| coord(i));
+        let mut left = 0;
+        for k in 1..ids.len() {
+            left += sizes[ids[k - 1]] as i64;
+            let (a, b) = (coord(ids[k - 1]), coord(ids[k]));
+            if a == b {
+                continue;
+            }
+            let want = left as f64 / total;
+            let cut = ((lo as f64 + (hi - lo) as f64 * want).round() as i16)
+                .max(a + 1)
+                .min(b);
+            let ratio = (cut - lo) as f64 / (hi - lo) as f64;
+            let balance = (k as f64 / ids.len() as f64 - 0.5).abs();
+            let cost = (ratio - want).abs() * penalty + 0.01 * balance;
+            if best.map_or(true, |b| cost < b.0) {
+                best = Some((cost, vertical, k, cut));
+            }
+        }
+    }
+    // 点はすべて異なるので、どちらかの方向には切れる
+    let (_, vertical, k, cut) = best.unwrap();
+    let (first, second) = if vertical {
+        ids.sort_by_key(|&i| points[i].0);
+        (
+            Rect::new(region.x1, cut, region.y1, region.y2),
+            Rect::new(cut, region.x2, region.y1, region.y2),
+        )
+    } else {
+        ids.sort_by_key(|&i| points[i].1);
+        (
+            Rect::new(region.x1, region.x2, region.y1, cut),
+            Rect::new(region.x1, region.x2, cut, region.y2),
+        )
+    };
+    (first, second, k)
+}
+
+/// region の中で (x, y) を含み、面積が r 以下で最大に近い長方形
+fn fit(region: &Rect, (x, y): (i16, i16), r: i32) -> Rect {
+    let width = (region.x2 - region.x1) as i32;
+    let height = (region.y2 - region.y1) as i32;
+    if width * height <= r {
+        return region.clone();
+    }
+    let mut w = ((r as f64).sqrt().round() as i32).max(1).min(width);
+    let h = (r / w).max(1).min(height);
+    w = (r / h).max(1).min(width);
+    let (w, h) = (w as i16, h as i16);
+    let x1 = (x - w / 2).max(region.x1).min(region.x2 - w);
+    let y1 = (y - h / 2).max(region.y1).min(region.y2 - h);
+    Rect::new(x1, x1 + w, y1, y1 + h)
+}
+
+#[cfg(test)]
+mod tests {
+    use proconio::source::once::OnceSource;
+
+    use super::super::{calc_score, parse_source};
+    use super::*;
+
+    #[test]
+    fn test_guillotine() {
+        let input = parse_source(OnceSource::new(
+            include_str!("../../tools/in/0000.txt").as_bytes(),
+        ));
+        let rects = guillotine(&input.points, &input.sizes);
+        for (i, rect) in rects.iter().enumerate() {
+            assert!(0 <= rect.x1 && rect.x2 <= L && 0 <= rect.y1 && rect.y2 <= L);
+            assert!(rect.contain(input.points[i].0, input.points[i].1));
+            assert!(rects[i + 1..].iter().all(|other| !rect.intersect(other)));
+        }
+        let n = rects.len() as f64;
+        let unit = calc_score(&input.rects, &input.sizes).0 / n;
+        let score = calc_score(&rects, &input.sizes).0 / n;
+        assert!(score > 0.5 && score > unit);
+    }
+}
@@ -0,0 +1,70 @@
+use super::Rect;
+
+/// 最良解を丸ごと複製せずに持つための undo log
+///
+/// 最良解になってから変わった長方形について、最良解での値を最初に変わったときだけ積む。
+/// 最良解は今の配置にこれを書き戻せば得られるので、更新は積んだ数だけの手間で済む。
+pub(crate) struct Journal {
+    undo: Vec<(usize, Rect)>,
+    logged: Vec<bool>,
+}
+
+impl Journal {
+    pub(crate) fn new(n: usize) -> Journal {
+        Journal {
+            undo: Vec::new(),
+            logged: vec![false; n],
+        }
+    }
+
+    /// i 番目を変える前に、今の値 old で呼ぶ
+    #[inline]
+    pub(crate) fn record(&mut self, i: usize, old: &Rect) {
+        if !self.logged[i] {
+            self.logged[i] = true;
+            self.undo.push((i, old.clone()));
+        }
+    }
+
+    /// 今の配置を最良解とする
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        for (i, _) in self.undo.drain(..) {
+            self.logged[i] = false;
+        }
+    }
+
+    /// 今の配置 rects を最良解に戻す
+    pub(crate) fn restore(&self, rects: &mut [Rect]) {
+        for (i, rect) in self.undo.iter() {
+            rects[*i] = rect.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal() {
+        let best = vec![Rect::new(0, 1, 0, 1), Rect::new(1, 2, 1, 2)];
+        let mut rects = best.clone();
+        let mut journal = Journal::new(2);
+        for d in 1..4 {
+            journal.record(0, &rects[0]);
+            rects[0] = Rect::new(0, 1 + d, 0, 1);
+        }
+        assert_eq!(journal.undo.len(), 1);
+        let mut restored = rects.clone();
+        journal.restore(&mut restored);
+        assert_eq!(restored[0].x2, 1);
+
+        journal.clear();
+        journal.record(1, &rects[1]);
+        rects[1] = Rect::new(1, 3, 1, 2);
+        let mut restored = rects.clone();
+        journal.restore(&mut restored);
+        assert_eq!((restored[0].x2, restored[1].x2), (4, 2));
+    }
+}
@@ -8,13 +8,57 @@ use rand::{
     distributions::{Distribution, Uniform},
     Rng, RngCore,
 };
+#[cfg(test)]
 use rand_pcg::Mcg128Xsl64;
+
+use acceptance::Criteria;
+use journal::Journal;
 #[cfg(feature = "learn")]
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use transaction::Transaction;
+
+mod acceptance;
+mod adjacency;
+mod bound;
+mod compact;
+mod decompose;
+mod free;
+mod init;
+mod journal;
+mod lns;
+mod memetic;
+mod params;
+mod portfolio;
+mod rng;
+mod schedule;
+mod table;
+mod tabu;
+mod tempering;
+mod transaction;
+
+pub use acceptance::{Acceptance, Criterion, FastMetropolis, Metropolis};
+pub use adjacency::{Adjacency, Contact};
+pub use bound::{upper_bound, Bound};
+pub use compact::{compact, Toward};
+pub use decompose::{decompose, Decompose};
+pub use free::{free_space, FreeSpace};
+pub use init::{guillotine, Init};
+pub use memetic::{memetic, Memetic};
+pub use params::{
+    get_params, Lns, McParams, ParamsError, Stagnation, StagnationAction, DEFAULT_PARAMS,
+};
+pub use portfolio::{portfolio, Portfolio};
+pub use rng::{DefaultRng, SeedRng, WyRand, Xorshift64Star};
+pub use schedule::Schedule;
+pub use table::{ParamsEntry, ParamsTable, PARAMS_TABLE};
+pub use tabu::{tabu, Tabu};
+pub use tempering::{tempering, Tempering};
+#[cfg(feature = "learn")]
+pub use {params::PARAMS_ENV, table::PARAMS_TABLE_ENV};
 
 const L: i16 = 10_000;
 const Q_MIN: i16 = L / 2 / 2 + 1;
-const LAYER1_OFFSET: u8 = (4 - 1) / 3;
+const LAYER1_OFFSET: u8 = 1;
 const LAYER2_OFFSET: u8 = (4 * 4 - 1) / 3;
 
 /// 最大4bitの情報がある n を受け取る
@@ -71,12 +115,65 @@ pub fn parent_gid(gid: u8) -> u8 {
 pub fn children_gid_range(gid: u8) -> std::ops::Range<u8> {
     let start = (gid - LAYER1_OFFSET) * 4 + LAYER2_OFFSET;
     let end = (gid - LAYER1_OFFSET + 1) * 4 + LAYER2_OFFSET;
-    return start..end;
+    start..end
 }
 
 #[derive(Debug)]
 pub struct QTree {
     grid: Vec<Vec<u8>>,
+    /// grid と同じ並びで持つ座標（soa feature）
+    #[cfg(feature = "soa")]
+    coords: Vec<Coords>,
+    /// i 番目の長方形の grid の中での位置（soa feature）
+    #[cfg(feature = "soa")]
+    pos: Vec<u8>,
+}
+
+/// 1つのセルの長方形を struct of arrays で持つ
+/// 重なりの判定を分岐なしで全部に対して行うので、自動ベクトル化される
+#[cfg(feature = "soa")]
+#[derive(Debug, Clone, Default)]
+struct Coords {
+    x1: Vec<i16>,
+    x2: Vec<i16>,
+    y1: Vec<i16>,
+    y2: Vec<i16>,
+}
+
+#[cfg(feature = "soa")]
+impl Coords {
+    fn push(&mut self, rect: &Rect) {
+        self.x1.push(rect.x1);
+        self.x2.push(rect.x2);
+        self.y1.push(rect.y1);
+        self.y2.push(rect.y2);
+    }
+
+    fn set(&mut self, k: usize, rect: &Rect) {
+        self.x1[k] = rect.x1;
+        self.x2[k] = rect.x2;
+        self.y1[k] = rect.y1;
+        self.y2[k] = rect.y2;
+    }
+
+    fn swap_remove(&mut self, k: usize) {
+        self.x1.swap_remove(k);
+        self.x2.swap_remove(k);
+        self.y1.swap_remove(k);
+        self.y2.swap_remove(k);
+    }
+
+    #[inline]
+    fn intersect(&self, grow: &Rect) -> bool {
+        let n = self.x1.len();
+        let (x1, x2, y1, y2) = (&self.x1[..n], &self.x2[..n], &self.y1[..n], &self.y2[..n]);
+        let mut hit = false;
+        for k in 0..n {
+            hit |= (x2[k].min(grow.x2) > x1[k].max(grow.x1))
+                & (y2[k].min(grow.y2) > y1[k].max(grow.y1));
+        }
+        hit
+    }
 }
 
 impl QTree {
@@ -86,13 +183,29 @@ impl QTree {
         for _ in 0..N {
             grid.push(Vec::new());
         }
+        #[cfg(feature = "soa")]
+        let mut coords = vec![Coords::default(); N];
+        #[cfg(feature = "soa")]
+        let mut pos = Vec::with_capacity(rects.len());
         for (i, rect) in rects.iter().enumerate() {
             let gid = get_gid(rect) as usize;
+            #[cfg(feature = "soa")]
+            {
+                pos.push(grid[gid].len() as u8);
+                coords[gid].push(rect);
+            }
             grid[gid].push(i as u8);
         }
-        QTree { grid }
+        QTree {
+            grid,
+            #[cfg(feature = "soa")]
+            coords,
+            #[cfg(feature = "soa")]
+            pos,
+        }
     }
 
+    #[cfg(not(feature = "soa"))]
     fn intersect_one_grid(&self, gid: u8, grow: &Rect, rects: &[Rect]) -> bool {
         unsafe {
             self.grid
@@ -102,6 +215,10 @@ impl QTree {
         }
     }
 
+    #[cfg(feature = "soa")]
+    fn intersect_one_grid(&self, gid: u8, grow: &Rect, _rects: &[Rect]) -> bool {
+        unsafe { self.coords.get_unchecked(gid as usize).intersect(grow) }
+    }
     pub fn intersect_to_parent(&self, mut gid: u8, grow: &Rect, rects: &[Rect]) -> bool {
         loop {
             if self.intersect_one_grid(gid, grow, rects) {
@@ -135,6 +252,33 @@ impl QTree {
         false
     }
 
+    /// grow と重なる長方形の番号をすべて f に渡す
+    /// intersect と同じく、grow のセルの祖先と子だけを見る
+    pub fn for_each_intersect(&self, grow: &Rect, rects: &[Rect], mut f: impl FnMut(usize)) {
+        let mut visit = |gid: u8| {
+            for &j in self.grid[gid as usize].iter() {
+                if grow.intersect(&rects[j as usize]) {
+                    f(j as usize);
+                }
+            }
+        };
+        let gid = get_gid(grow);
+        if gid == 0 {
+            (0..self.grid.len() as u8).for_each(visit);
+            return;
+        }
+        let mut g = gid;
+        while g != 0 {
+            visit(g);
+            g = parent_gid(g);
+        }
+        visit(0);
+        if gid < LAYER2_OFFSET {
+            children_gid_range(gid).for_each(visit);
+        }
+    }
+
+    #[cfg(not(feature = "soa"))]
     pub fn update(&mut self, new: &Rect, old: &Rect, i: usize) {
         let old_gid = get_gid(old) as usize;
         let new_gid = get_gid(new) as usize;
@@ -148,6 +292,26 @@ impl QTree {
         self.grid[old_gid].swap_remove(pos);
         self.grid[new_gid].push(i as u8);
     }
+
+    /// 同じセルのままでも座標を書き換える
+    #[cfg(feature = "soa")]
+    pub fn update(&mut self, new: &Rect, old: &Rect, i: usize) {
+        let old_gid = get_gid(old) as usize;
+        let new_gid = get_gid(new) as usize;
+        let k = self.pos[i] as usize;
+        if old_gid == new_gid {
+            self.coords[old_gid].set(k, new);
+            return;
+        }
+        self.grid[old_gid].swap_remove(k);
+        self.coords[old_gid].swap_remove(k);
+        if let Some(&moved) = self.grid[old_gid].get(k) {
+            self.pos[moved as usize] = k as u8;
+        }
+        self.pos[i] = self.grid[new_gid].len() as u8;
+        self.grid[new_gid].push(i as u8);
+        self.coords[new_gid].push(new);
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -158,6 +322,14 @@ pub struct Rect {
     pub y2: i16,
 }
 
+/// 盤面全体
+pub const BOARD: Rect = Rect {
+    x1: 0,
+    x2: L,
+    y1: 0,
+    y2: L,
+};
+
 impl Rect {
     pub fn new(x1: i16, x2: i16, y1: i16, y2: i16) -> Rect {
         Rect { x1, x2, y1, y2 }
@@ -183,7 +355,32 @@ impl Rect {
     }
 
     pub fn slide_x(&self, d: i16) -> Option<Rect> {
-        if self.x1 + d < 0 || L < self.x2 + d {
+        self.slide_x_within(d, &BOARD)
+    }
+
+    pub fn slide_y(&self, d: i16) -> Option<Rect> {
+        self.slide_y_within(d, &BOARD)
+    }
+
+    pub fn grow_x1(&self, d: i16) -> Option<Rect> {
+        self.grow_x1_within(d, &BOARD)
+    }
+
+    pub fn grow_x2(&self, d: i16) -> Option<Rect> {
+        self.grow_x2_within(d, &BOARD)
+    }
+
+    pub fn grow_y1(&self, d: i16) -> Option<Rect> {
+        self.grow_y1_within(d, &BOARD)
+    }
+
+    pub fn grow_y2(&self, d: i16) -> Option<Rect> {
+        self.grow_y2_within(d, &BOARD)
+    }
+
+    /// bounds の外に出るなら None
+    pub fn slide_x_within(&self, d: i16, bounds: &Rect) -> Option<Rect> {
+        if self.x1 + d < bounds.x1 || bounds.x2 < self.x2 + d {
             None
         } else {
             Some(Rect {
@@ -195,8 +392,8 @@ impl Rect {
         }
     }
 
-    pub fn slide_y(&self, d: i16) -> Option<Rect> {
-        if self.y1 + d < 0 || L < self.y2 + d {
+    pub fn slide_y_within(&self, d: i16, bounds: &Rect) -> Option<Rect> {
+        if self.y1 + d < bounds.y1 || bounds.y2 < self.y2 + d {
             None
         } else {
             Some(Rect {
@@ -208,8 +405,8 @@ impl Rect {
         }
     }
 
-    pub fn grow_x1(&self, d: i16) -> Option<Rect> {
-        if self.x1 + d < 0 || self.x2 <= self.x1 + d {
+    pub fn grow_x1_within(&self, d: i16, bounds: &Rect) -> Option<Rect> {
+        if self.x1 + d < bounds.x1 || self.x2 <= self.x1 + d {
             None
         } else {
             Some(Rect {
@@ -221,8 +418,8 @@ impl Rect {
         }
     }
 
-    pub fn grow_x2(&self, d: i16) -> Option<Rect> {
-        if self.x2 + d <= self.x1 || L < self.x2 + d {
+    pub fn grow_x2_within(&self, d: i16, bounds: &Rect) -> Option<Rect> {
+        if self.x2 + d <= self.x1 || bounds.x2 < self.x2 + d {
             None
         } else {
             Some(Rect {
@@ -234,8 +431,8 @@ impl Rect {
         }
     }
 
-    pub fn grow_y1(&self, d: i16) -> Option<Rect> {
-        if self.y1 + d < 0 || self.y2 <= self.y1 + d {
+    pub fn grow_y1_within(&self, d: i16, bounds: &Rect) -> Option<Rect> {
+        if self.y1 + d < bounds.y1 || self.y2 <= self.y1 + d {
             None
         } else {
             Some(Rect {
@@ -247,8 +444,8 @@ impl Rect {
         }
     }
 
-    pub fn grow_y2(&self, d: i16) -> Option<Rect> {
-        if self.y2 + d <= self.y1 || L < self.y2 + d {
+    pub fn grow_y2_within(&self, d: i16, bounds: &Rect) -> Option<Rect> {
+        if self.y2 + d <= self.y1 || bounds.y2 < self.y2 + d {
             None
         } else {
             Some(Rect {
@@ -301,29 +498,6 @@ fn intersect(new: &Rect, rects: &[Rect]) -> bool {
     rects.iter().any(|rect| new.intersect(rect))
 }
 
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "learn", derive(Deserialize))]
-pub struct McParams {
-    temp0: f64,
-    temp1: f64,
-    slide_d_start: f64,
-    slide_d_end: f64,
-    grow_d1_start: f64,
-    grow_d1_end: f64,
-    grow_d2_start: f64,
-    grow_d2_end: f64,
-    grow_d3_start: f64,
-    grow_d3_end: f64,
-    weight_slide_start: f64,
-    weight_slide_end: f64,
-    weight_d1_start: f64,
-    weight_d1_end: f64,
-    weight_d2_start: f64,
-    weight_d2_end: f64,
-    weight_d3_start: f64,
-    weight_d3_end: f64,
-}
-
 fn calc_score(rects: &[Rect], sizes: &[i32]) -> (f64, Vec<f64>) {
     let mut score = 0.0;
     let mut scores = Vec::with_capacity(rects.len());
@@ -335,196 +509,572 @@ fn calc_score(rects: &[Rect], sizes: &[i32]) -> (f64, Vec<f64>) {
     (score, scores)
 }
 
-fn mc(rng: &mut Mcg128Xsl64, params: McParams, input: &Input, limit: u64) -> (f64, Vec<Rect>) {
-    let now = Instant::now();
-    let limit = Duration::from_millis(limit);
+/// mc をいつまで回すか
+#[derive(Debug, Clone, Copy)]
+pub enum Budget {
+    Time(Duration),
+    /// 提案回数（2000 回単位に切り上げ）
+    Iterations(u64),
+}
 
-    let mut rects = input.rects.to_vec();
-    let (mut score, mut scores) = calc_score(&rects, &input.sizes);
+/// mc の提案の内訳
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// 提案した数
+    pub all: u64,
+    /// 盤面内で変形できた数
+    pub tried: u64,
+    /// 点を含み、受理判定を通った数
+    pub valid: u64,
+    /// 重なりもなく受理した数
+    pub ac: u64,
+    /// 停滞を検出したときの記録
+    pub stagnations: Vec<StagnationEvent>,
+    /// レプリカ交換法で隣り合う組ごとの (試行, 交換) 回数
+    pub exchanges: Vec<(u64, u64)>,
+    /// 複数スタートのときのスタートごとの (提案数, 最良スコア)
+    pub starts: Vec<(u64, f64)>,
+    /// LNS の (試行, 採用) 回数
+    pub lns: (u64, u64),
+    /// 接している長方形を追うときの (提案数, 4辺とも塞がれた長方形の数)
+    pub boxed_in: Vec<(u64, usize)>,
+    /// 盤面を分けたときの領域ごとの (点の数, 最良スコア)
+    pub regions: Vec<(usize, f64)>,
+}
 
-    let index_sample = Uniform::new(0, rects.len());
+#[derive(Debug, Clone)]
+pub struct StagnationEvent {
+    /// それまでの提案数
+    pub at: u64,
+    pub t: f64,
+    /// 検出時の現在スコアと最良スコア（0〜1）
+    pub score: f64,
+    pub best_score: f64,
+}
 
-    let mut qtree = QTree::new(&rects);
-    let mut best = rects.clone();
-    let mut best_score = score;
-    loop {
-        let elapsed = now.elapsed();
-        if elapsed > limit {
-            return (best_score / scores.len() as f64, best);
+/// 焼きなましの途中の状態
+struct State {
+    rects: Vec<Rect>,
+    scores: Vec<f64>,
+    score: f64,
+    qtree: QTree,
+    best_score: f64,
+    /// 最良解は journal で今の配置から戻して作る
+    journal: Journal,
+    /// begin してから commit / rollback するまでの変更
+    tx: Option<Transaction>,
+    /// Some なら受理するたびに更新する
+    adjacency: Option<Adjacency>,
+    /// 長方形を動かせる範囲
+    bounds: Rect,
+}
+
+impl State {
+    fn new(rects: Vec<Rect>, sizes: &[i32]) -> State {
+        let (score, scores) = calc_score(&rects, sizes);
+        let qtree = QTree::new(&rects);
+        State {
+            best_score: score,
+            journal: Journal::new(rects.len()),
+            tx: None,
+            adjacency: None,
+            bounds: BOARD,
+            rects,
+            scores,
+            score,
+            qtree,
         }
-        let t = elapsed.as_secs_f64() / limit.as_secs_f64();
-        let beta = 1.0 / (params.temp0.powf(1.0 - t) * params.temp1.powf(t));
+    }
 
-        let slide_d = Uniform::new(
-            1,
-            2 + (params.slide_d_start * (1.0 - t) + params.slide_d_end * t) as i16,
-        );
-        let grow_d1 = Uniform::new(
-            1,
-            2 + (params.grow_d1_start * (1.0 - t) + params.grow_d1_end * t) as i16,
-        );
-        let grow_d2 = Uniform::new(
-            1,
-            2 + (params.grow_d2_start * (1.0 - t) + params.grow_d2_end * t) as i16,
-        );
-        let grow_d3 = Uniform::new(
-            1,
-            2 + (params.grow_d3_start * (1.0 - t) + params.grow_d3_end * t) as i16,
-        );
-        let w_slide = params.weight_slide_start * (1.0 - t) + params.weight_slide_end * t;
-        let w_d1 = params.weight_d1_start * (1.0 - t) + params.weight_d1_end * t;
-        let w_d2 = params.weight_d2_start * (1.0 - t) + params.weight_d2_end * t;
-        let w_d3 = params.weight_d3_start * (1.0 - t) + params.weight_d3_end * t;
+    /// i 番目を rect にする（重なりは呼び出し側で確かめる）
+    fn set(&mut self, i: usize, rect: Rect, sizes: &[i32]) {
+        self.journal.record(i, &self.rects[i]);
+        if let Some(tx) = &mut self.tx {
+            tx.record(i, &self.rects[i]);
+        }
+        self.qtree.update(&rect, &self.rects[i], i);
+        let s = rect.score(sizes[i]);
+        self.score += s - self.scores[i];
+        self.scores[i] = s;
+        self.rects[i] = rect;
+        if let Some(adjacency) = &mut self.adjacency {
+            adjacency.update(i, &self.rects, &self.qtree);
+        }
+    }
+
+    /// これ以降、接している長方形を追う
+    fn track_adjacency(&mut self) {
+        self.adjacency = Some(Adjacency::new(&self.rects));
+    }
+
+    /// rects を差し替えたあとに scores と qtree を作り直す
+    fn reset(&mut self, rects: Vec<Rect>, sizes: &[i32]) {
+        for (i, rect) in self.rects.iter().enumerate() {
+            self.journal.record(i, rect);
+        }
+        let (score, scores) = calc_score(&rects, sizes);
+        self.qtree = QTree::new(&rects);
+        if self.adjacency.is_some() {
+            self.adjacency = Some(Adjacency::new(&rects));
+        }
+        self.rects = rects;
+        self.scores = scores;
+        self.score = score;
+    }
+
+    /// 今の配置を最良解にする
+    fn update_best(&mut self) {
+        self.best_score = self.score;
+        self.journal.clear();
+    }
+
+    fn best(&self) -> Vec<Rect> {
+        let mut best = self.rects.clone();
+        self.journal.restore(&mut best);
+        best
+    }
+
+    fn into_best(mut self) -> Vec<Rect> {
+        self.journal.restore(&mut self.rects);
+        self.rects
+    }
+}
+
+/// 時刻 t での近傍の選び方
+struct Moves {
+    slide_d: Uniform<i16>,
+    grow_d1: Uniform<i16>,
+    grow_d2: Uniform<i16>,
+    grow_d3: Uniform<i16>,
+    p0: f64,
+    p1: f64,
+    p2: f64,
+    /// 長方形を動かせる範囲
+    bounds: Rect,
+}
+
+impl Moves {
+    fn new(params: &McParams, t: f64, bounds: &Rect) -> Moves {
+        let w_slide = params.weight_slide.at(t);
+        let w_d1 = params.weight_d1.at(t);
+        let w_d2 = params.weight_d2.at(t);
+        let w_d3 = params.weight_d3.at(t);
         let p0 = w_slide / (w_slide + w_d1 + w_d2 + w_d3);
         let p1 = p0 + w_d1 / (w_slide + w_d1 + w_d2 + w_d3);
         let p2 = p1 + w_d2 / (w_slide + w_d1 + w_d2 + w_d3);
+        Moves {
+            slide_d: Uniform::new(1, 2 + params.slide_d.at(t) as i16),
+            grow_d1: Uniform::new(1, 2 + params.grow_d1.at(t) as i16),
+            grow_d2: Uniform::new(1, 2 + params.grow_d2.at(t) as i16),
+            grow_d3: Uniform::new(1, 2 + params.grow_d3.at(t) as i16),
+            p0,
+            p1,
+            p2,
+            bounds: bounds.clone(),
+        }
+    }
 
-        let rect_slide = |rng: &mut Mcg128Xsl64, rect: &Rect| {
-            let d = slide_d.sample(rng);
-            match rng.next_u32() % 4 {
-                0 => rect.slide_x(d),
-                1 => rect.slide_x(-d),
-                2 => rect.slide_y(d),
-                3 => rect.slide_y(-d),
-                _ => unreachable!(),
+    #[inline]
+    fn propose<R: RngCore>(&self, rng: &mut R, rect: &Rect) -> Option<Rect> {
+        let p = rng.gen::<f64>();
+        if p < self.p0 {
+            self.slide(rng, rect)
+        } else if p < self.p1 {
+            self.grow_d1(rng, rect)
+        } else if p < self.p2 {
+            self.grow_d2(rng, rect)
+        } else {
+            self.grow_d3(rng, rect)
+        }
+    }
+
+    #[inline]
+    fn slide<R: RngCore>(&self, rng: &mut R, rect: &Rect) -> Option<Rect> {
+        let bounds = &self.bounds;
+        let d = self.slide_d.sample(rng);
+        match rng.next_u32() % 4 {
+            0 => rect.slide_x_within(d, bounds),
+            1 => rect.slide_x_within(-d, bounds),
+            2 => rect.slide_y_within(d, bounds),
+            3 => rect.slide_y_within(-d, bounds),
+            _ => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn grow_d1<R: RngCore>(&self, rng: &mut R, rect: &Rect) -> Option<Rect> {
+        let bounds = &self.bounds;
+        let d = self.grow_d1.sample(rng);
+        match rng.next_u32() % 8 {
+            0 => rect.grow_x1_within(d, bounds),
+            1 => rect.grow_x1_within(-d, bounds),
+            2 => rect.grow_x2_within(d, bounds),
+            3 => rect.grow_x2_within(-d, bounds),
+            4 => rect.grow_y1_within(d, bounds),
+            5 => rect.grow_y1_within(-d, bounds),
+            6 => rect.grow_y2_within(d, bounds),
+            7 => rect.grow_y2_within(-d, bounds),
+            _ => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn grow_d2<R: RngCore>(&self, rng: &mut R, rect: &Rect) -> Option<Rect> {
+        let (grow_d2, bounds) = (&self.grow_d2, &self.bounds);
+        let d1 = grow_d2.sample(rng);
+        match rng.next_u32() % 8 {
+            0 => rect
+                .grow_x1_within(d1, bounds)
+                .and_then(|rect| rect.grow_y1_within(-grow_d2.sample(rng), bounds)),
+            1 => rect
+                .grow_x1_within(-d1, bounds)
+                .and_then(|rect| rect.grow_y1_within(grow_d2.sample(rng), bounds)),
+            2 => rect
+                .grow_x1_within(d1, bounds)
+                .and_then(|rect| rect.grow_y2_within(grow_d2.sample(rng), bounds)),
+            3 => rect
+                .grow_x1_within(-d1, bounds)
+                .and_then(|rect| rect.grow_y2_within(-grow_d2.sample(rng), bounds)),
+            4 => rect
+                .grow_x2_within(d1, bounds)
+                .and_then(|rect| rect.grow_y1_within(grow_d2.sample(rng), bounds)),
+            5 => rect
+                .grow_x2_within(-d1, bounds)
+                .and_then(|rect| rect.grow_y1_within(-grow_d2.sample(rng), bounds)),
+            6 => rect
+                .grow_x2_within(d1, bounds)
+                .and_then(|rect| rect.grow_y2_within(-grow_d2.sample(rng), bounds)),
+            7 => rect
+                .grow_x2_within(-d1, bounds)
+                .and_then(|rect| rect.grow_y2_within(grow_d2.sample(rng), bounds)),
+            _ => unreachable!(),
+        }
+    }
+
+    #[inline]
+    fn grow_d3<R: RngCore>(&self, rng: &mut R, rect: &Rect) -> Option<Rect> {
+        let (grow_d3, bounds) = (&self.grow_d3, &self.bounds);
+        let d1 = grow_d3.sample(rng);
+        match rng.next_u32() % 4 {
+            0 => rect
+                .grow_x1_within(d1, bounds)
+                .and_then(|rect| rect.grow_x2_within(grow_d3.sample(rng), bounds)),
+            1 => rect
+                .grow_x1_within(-d1, bounds)
+                .and_then(|rect| rect.grow_x2_within(-grow_d3.sample(rng), bounds)),
+            2 => rect
+                .grow_y1_within(d1, bounds)
+                .and_then(|rect| rect.grow_y2_within(grow_d3.sample(rng), bounds)),
+            3 => rect
+                .grow_y1_within(-d1, bounds)
+                .and_then(|rect| rect.grow_y2_within(-grow_d3.sample(rng), bounds)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// criterion で受理を判定しながら count 回提案する
+/// 最良スコアを更新したら true
+fn sweep<R: RngCore, C: Criterion>(
+    rng: &mut R,
+    state: &mut State,
+    moves: &Moves,
+    input: &Input,
+    criterion: &mut C,
+    count: u64,
+    stats: &mut Stats,
+) -> bool {
+    let State {
+        rects,
+        scores,
+        score,
+        qtree,
+        best_score,
+        journal,
+        // 提案ごとの変更はトランザクションに入れない
+        tx: _,
+        adjacency,
+        // 範囲は moves が持つ
+        bounds: _,
+    } = state;
+    let index_sample = Uniform::new(0, rects.len());
+    let mut improved = false;
+    let mut current = scores.iter().fold(0.0, |x, y| x + *y);
+
+    let (mut tried, mut valid, mut ac) = (0, 0, 0);
+    for _ in 0..count {
+        // 下限を先に引ける判定では、スコアの差だけで棄却して点や重なりを調べない
+        let bound = criterion.presample(rng);
+        let i = index_sample.sample(rng);
+        let rect = rects.get(i).unwrap();
+
+        if let Some(new) = moves.propose(rng, rect) {
+            tried += 1;
+            let new_score = new.score(input.sizes[i]);
+            let score_diff = new_score - scores[i];
+            if let Some(bound) = bound {
+                if score_diff < 0.0 && score_diff <= bound {
+                    continue;
+                }
             }
-        };
-        let rect_grow_d1 = |rng: &mut Mcg128Xsl64, rect: &Rect| {
-            let d = grow_d1.sample(rng);
-            match rng.next_u32() % 8 {
-                0 => rect.grow_x1(d),
-                1 => rect.grow_x1(-d),
-                2 => rect.grow_x2(d),
-                3 => rect.grow_x2(-d),
-                4 => rect.grow_y1(d),
-                5 => rect.grow_y1(-d),
-                6 => rect.grow_y2(d),
-                7 => rect.grow_y2(-d),
-                _ => unreachable!(),
+            if !new.contain(input.points[i].0, input.points[i].1) {
+                continue;
             }
-        };
-        let rect_grow_d2 = |rng: &mut Mcg128Xsl64, rect: &Rect| {
-            let d1 = grow_d2.sample(rng);
-            match rng.next_u32() % 8 {
-                0 => rect
-                    .grow_x1(d1)
-                    .and_then(|rect| rect.grow_y1(-grow_d2.sample(rng))),
-                1 => rect
-                    .grow_x1(-d1)
-                    .and_then(|rect| rect.grow_y1(grow_d2.sample(rng))),
-                2 => rect
-                    .grow_x1(d1)
-                    .and_then(|rect| rect.grow_y2(grow_d2.sample(rng))),
-                3 => rect
-                    .grow_x1(-d1)
-                    .and_then(|rect| rect.grow_y2(-grow_d2.sample(rng))),
-                4 => rect
-                    .grow_x2(d1)
-                    .and_then(|rect| rect.grow_y1(grow_d2.sample(rng))),
-                5 => rect
-                    .grow_x2(-d1)
-                    .and_then(|rect| rect.grow_y1(-grow_d2.sample(rng))),
-                6 => rect
-                    .grow_x2(d1)
-                    .and_then(|rect| rect.grow_y2(-grow_d2.sample(rng))),
-                7 => rect
-                    .grow_x2(-d1)
-                    .and_then(|rect| rect.grow_y2(grow_d2.sample(rng))),
-                _ => unreachable!(),
+            if bound.is_some() || criterion.accept(rng, score_diff, current) {
+                valid += 1;
+                if let Some(grow) = rect.grow_rect(&new) {
+                    if qtree.intersect(&grow, rects) {
+                        continue;
+                    }
+                }
+                ac += 1;
+                journal.record(i, rect);
+                qtree.update(&new, rect, i);
+                scores[i] = new_score;
+                rects[i] = new;
+                if let Some(adjacency) = adjacency {
+                    adjacency.update(i, rects, qtree);
+                }
+                current += score_diff;
+                if current > *best_score {
+                    *best_score = current;
+                    journal.clear();
+                    improved = true;
+                }
             }
-        };
-        let rect_grow_d3 = |rng: &mut Mcg128Xsl64, rect: &Rect| {
-            let d1 = grow_d3.sample(rng);
-            match rng.next_u32() % 4 {
-                0 => rect
-                    .grow_x1(d1)
-                    .and_then(|rect| rect.grow_x2(grow_d3.sample(rng))),
-                1 => rect
-                    .grow_x1(-d1)
-                    .and_then(|rect| rect.grow_x2(-grow_d3.sample(rng))),
-                2 => rect
-                    .grow_y1(d1)
-                    .and_then(|rect| rect.grow_y2(grow_d3.sample(rng))),
-                3 => rect
-                    .grow_y1(-d1)
-                    .and_then(|rect| rect.grow_y2(-grow_d3.sample(rng))),
-                _ => unreachable!(),
+        }
+    }
+    *score = current;
+    stats.all += count;
+    stats.tried += tried;
+    stats.valid += valid;
+    stats.ac += ac;
+    improved
+}
+
+/// 経過の割合 t ∈ [0, 1]
+/// 予算を使い切っていたら None
+fn progress(budget: Budget, now: Instant, all: u64) -> Option<f64> {
+    match budget {
+        Budget::Time(limit) => {
+            let elapsed = now.elapsed();
+            if elapsed > limit {
+                return None;
             }
-        };
-        score = scores.iter().fold(0.0, |x, y| x + *y);
-
-        for _ in 0..2000 {
-            let i = index_sample.sample(rng);
-            let rect = rects.get(i).unwrap();
-
-            let p = rng.gen::<f64>();
-            let new = if p < p0 {
-                rect_slide(rng, rect)
-            } else if p < p1 {
-                rect_grow_d1(rng, rect)
-            } else if p < p2 {
-                rect_grow_d2(rng, rect)
-            } else {
-                rect_grow_d3(rng, rect)
-            };
-            if let Some(new) = new {
-                if !new.contain(input.points[i].0, input.points[i].1) {
-                    continue;
+            Some(elapsed.as_secs_f64() / limit.as_secs_f64())
+        }
+        Budget::Iterations(n) => {
+            if all >= n {
+                return None;
+            }
+            Some(all as f64 / n as f64)
+        }
+    }
+}
+
+fn temperature(params: &McParams, t: f64) -> f64 {
+    params.temp0.powf(1.0 - t) * params.temp1.powf(t)
+}
+
+/// criterion を温度 temp に合わせてから 2000 回提案する
+fn sweep_with<R: RngCore, C: Criterion>(
+    criterion: &mut C,
+    rng: &mut R,
+    state: &mut State,
+    moves: &Moves,
+    input: &Input,
+    temp: f64,
+    stats: &mut Stats,
+) -> bool {
+    criterion.prepare(temp, state.score, state.best_score);
+    sweep(rng, state, moves, input, criterion, 2000, stats)
+}
+
+/// criterion の中身の型ごとに sweep_with を呼ぶ
+fn sweep_criteria<R: RngCore>(
+    criterion: &mut Criteria,
+    rng: &mut R,
+    state: &mut State,
+    moves: &Moves,
+    input: &Input,
+    temp: f64,
+    stats: &mut Stats,
+) -> bool {
+    match criterion {
+        Criteria::Metropolis(c) => sweep_with(c, rng, state, moves, input, temp, stats),
+        Criteria::FastMetropolis(c) => sweep_with(c, rng, state, moves, input, temp, stats),
+        Criteria::Threshold(c) => sweep_with(c, rng, state, moves, input, temp, stats),
+        Criteria::GreatDeluge(c) => sweep_with(c, rng, state, moves, input, temp, stats),
+        Criteria::RecordToRecord(c) => sweep_with(c, rng, state, moves, input, temp, stats),
+        Criteria::LateAcceptance(c) => sweep_with(c, rng, state, moves, input, temp, stats),
+    }
+}
+
+/// 停滞の検出も含めて、途中から再開できる焼きなまし
+struct Annealer {
+    state: State,
+    stats: Stats,
+    /// 最後に最良スコアを更新したときの提案数
+    improved_at: u64,
+    /// 再加熱の (開始した提案数, 温度の倍率の対数)
+    reheat: Option<(u64, f64)>,
+    /// 最後に LNS を行ったときの提案数
+    lns_at: u64,
+    criterion: Criteria,
+}
+
+impl Annealer {
+    fn new(params: &McParams, input: &Input) -> Annealer {
+        Annealer::from_rects(params, input.rects.to_vec(), &input.sizes)
+    }
+
+    fn from_rects(params: &McParams, rects: Vec<Rect>, sizes: &[i32]) -> Annealer {
+        Annealer {
+            criterion: params.acceptance.criterion(sizes.len()),
+            state: State::new(rects, sizes),
+            stats: Stats::default(),
+            improved_at: 0,
+            reheat: None,
+            lns_at: 0,
+        }
+    }
+
+    /// 停滞したときの処理をする
+    fn stagnate(&mut self, action: &StagnationAction, params: &McParams, input: &Input, t: f64) {
+        let Annealer {
+            state,
+            stats,
+            improved_at,
+            reheat,
+            ..
+        } = self;
+        let temp = temperature(params, t);
+        let n = state.scores.len() as f64;
+        stats.stagnations.push(StagnationEvent {
+            at: stats.all,
+            t,
+            score: state.score / n,
+            best_score: state.best_score / n,
+        });
+        *improved_at = stats.all;
+        match *action {
+            StagnationAction::Reheat { fraction } => {
+                let boost = (params.temp0 * fraction / temp).ln();
+                if boost > 0.0 {
+                    *reheat = Some((stats.all, boost));
                 }
-                let new_score = new.score(input.sizes[i]);
-                let score_diff = new_score - scores[i];
-                if score_diff >= 0.0 || rng.gen::<f64>() < (score_diff * beta).exp() {
-                    if let Some(grow) = rect.grow_rect(&new) {
-                        if qtree.intersect(&grow, &rects) {
-                            continue;
-                        }
-                    }
-                    qtree.update(&new, rect, i);
-                    scores[i] = new_score;
-                    rects[i] = new;
-                    score += score_diff;
-                    if score > best_score {
-                        best_score = score;
-                        best = rects.clone();
+            }
+            StagnationAction::Restart => {
+                let best = state.best();
+                state.reset(best, &input.sizes);
+            }
+            StagnationAction::Compact { toward } => {
+                let mut rects = state.rects.clone();
+                compact::compact_within(&mut rects, &input.points, toward, &state.bounds);
+                for (i, rect) in rects.into_iter().enumerate() {
+                    let old = &state.rects[i];
+                    if (rect.x1, rect.y1) != (old.x1, old.y1) {
+                        state.set(i, rect, &input.sizes);
                     }
                 }
             }
+            StagnationAction::Perturb { k } => {
+                let scores = &state.scores;
+                let mut order = (0..scores.len()).collect::<Vec<_>>();
+                order.sort_by(|&a, &b| scores[a].partial_cmp(&scores[b]).unwrap());
+                for &i in order.iter().take(k) {
+                    // 1x1 は自分の点を含むので他と重ならない
+                    let (x, y) = input.points[i];
+                    state.set(i, Rect::new(x, x + 1, y, y + 1), &input.sizes);
+                }
+            }
         }
     }
-}
 
-const DEFAULT_PARAMS: McParams = McParams {
-    temp0: 0.10868564634648839,
-    temp1: 0.00029342425784192465,
-    slide_d_start: 59.0,
-    slide_d_end: 59.0,
-    grow_d1_start: 661.4780032749206,
-    grow_d1_end: 7.211273402804876,
-    grow_d2_start: 1532.4395218778254,
-    grow_d2_end: 3.178973798285788,
-    grow_d3_start: 660.2448737846898,
-    grow_d3_end: 5.668514832161116,
-    weight_slide_start: 0.056353565358393205,
-    weight_slide_end: 0.056353565358393205,
-    weight_d1_start: 0.1745393427756753,
-    weight_d1_end: 0.1745393427756753,
-    weight_d2_start: 0.7691070918659315,
-    weight_d2_end: 0.7691070918659315,
-    weight_d3_start: 0.0,
-    weight_d3_end: 0.0,
-};
+    /// 時刻 t で 2000 回提案する
+    fn step<R: RngCore>(&mut self, rng: &mut R, params: &McParams, input: &Input, t: f64) {
+        if let Some(stagnation) = &params.stagnation {
+            if self.stats.all - self.improved_at >= stagnation.window {
+                self.stagnate(&stagnation.action, params, input, t);
+            }
+        }
+        let Annealer {
+            state,
+            stats,
+            improved_at,
+            reheat,
+            lns_at,
+            criterion,
+        } = self;
+        let mut temp = temperature(params, t);
+        if let Some(stagnation) = &params.stagnation {
+            if let Some((start, boost)) = *reheat {
+                let s = (stats.all - start) as f64 / stagnation.window as f64;
+                if s < 1.0 {
+                    temp *= (boost * (1.0 - s)).exp();
+                } else {
+                    *reheat = None;
+                }
+            }
+        }
+        let moves = Moves::new(params, t, &state.bounds);
+        if let Some(lns) = &params.lns {
+            if stats.all - *lns_at >= lns.interval {
+                *lns_at = stats.all;
+                let best_score = state.best_score;
+                lns::destroy_and_repair(rng, state, &moves, input, lns, criterion, temp, stats);
+                if state.best_score > best_score {
+                    *improved_at = stats.all;
+                }
+            }
+        }
+        let improved = sweep_criteria(criterion, rng, state, &moves, input, temp, stats);
+        if improved {
+            *improved_at = stats.all;
+        }
+        if let Some(adjacency) = &state.adjacency {
+            stats
+                .boxed_in
+                .push((stats.all, adjacency.boxed_in(&state.rects)));
+        }
+    }
 
-#[cfg(feature = "learn")]
-fn get_params(arg: Option<String>) -> McParams {
-    arg.and_then(|arg| Some(serde_json::de::from_str(&arg).unwrap()))
-        .unwrap_or(DEFAULT_PARAMS)
+    /// (最良スコア（0〜1）, 最良解, 統計)
+    fn finish(self) -> (f64, Vec<Rect>, Stats) {
+        let n = self.state.scores.len() as f64;
+        (
+            self.state.best_score / n,
+            self.state.into_best(),
+            self.stats,
+        )
+    }
+}
+
+/// 1つの状態を焼きなます
+pub fn mc<R: RngCore>(
+    rng: &mut R,
+    params: &McParams,
+    input: &Input,
+    budget: Budget,
+) -> (f64, Vec<Rect>, Stats) {
+    anneal(rng, params, input, budget, Annealer::new(params, input))
 }
 
-#[cfg(not(feature = "learn"))]
-fn get_params(_arg: Option<String>) -> McParams {
-    DEFAULT_PARAMS
+fn anneal<R: RngCore>(
+    rng: &mut R,
+    params: &McParams,
+    input: &Input,
+    budget: Budget,
+    mut annealer: Annealer,
+) -> (f64, Vec<Rect>, Stats) {
+    let now = Instant::now();
+    while let Some(t) = progress(budget, now, annealer.stats.all) {
+        annealer.step(rng, params, input, t);
+    }
+    annealer.finish()
 }
 
+#[derive(Clone)]
 pub struct Input {
     pub rects: Vec<Rect>,
     pub points: Vec<(i16, i16)>,
@@ -553,10 +1103,275 @@ pub fn parse_source<R: BufRead, S: Source<R>>(source: S) -> Input {
     }
 }
 
-pub fn run(input: Input, arg: Option<String>) -> (f64, Vec<Rect>) {
-    let mut rng = Mcg128Xsl64::new(1);
-    let params = get_params(arg);
-    mc(&mut rng, params.clone(), &input, 4970)
+/// 1回の実行を再現するのに必要な設定
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "learn", derive(Deserialize, Serialize))]
+pub struct RunConfig {
+    pub seed: u64,
+    /// ミリ秒
+    pub limit: u64,
+    /// Some なら時間ではなく提案回数で打ち切る（limit は使わない）
+    /// 実行環境によらず結果が再現する
+    #[cfg_attr(feature = "learn", serde(default))]
+    pub iterations: Option<u64>,
+    pub params: McParams,
+    /// Some ならレプリカ交換法で解く
+    #[cfg_attr(
+        feature = "learn",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub tempering: Option<Tempering>,
+    /// Some なら予算を分けて複数回始める
+    #[cfg_attr(
+        feature = "learn",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub portfolio: Option<Portfolio>,
+    /// Some なら集団で解く
+    #[cfg_attr(
+        feature = "learn",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub memetic: Option<Memetic>,
+    /// Some なら焼きなましではなくタブー探索で解く（params, seed は使わない）
+    #[cfg_attr(
+        feature = "learn",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub tabu: Option<Tabu>,
+    /// Some なら盤面を分けて並列に解く
+    /// tempering, portfolio, memetic, tabu, decompose はどれか1つしか使えない
+    #[cfg_attr(
+        feature = "learn",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub decompose: Option<Decompose>,
+    /// 始める配置
+    #[cfg_attr(feature = "learn", serde(default))]
+    pub init: Init,
+    /// true なら接している長方形を追い、Stats::boxed_in に記録する
+    /// tempering, portfolio, memetic, tabu, decompose では使わない
+    #[cfg_attr(
+        feature = "learn",
+        serde(default, skip_serializing_if = "std::ops::Not::not")
+    )]
+    pub adjacency: bool,
+}
+
+impl RunConfig {
+    pub fn new(arg: Option<String>, n: usize) -> Result<RunConfig, ParamsError> {
+        Ok(RunConfig::with_params(get_params(arg, n)?, 1, 4970, None))
+    }
+
+    /// params で普通に焼きなます設定（tempering などのモードはすべて使わない）
+    pub fn with_params(
+        params: McParams,
+        seed: u64,
+        limit: u64,
+        iterations: Option<u64>,
+    ) -> RunConfig {
+        RunConfig {
+            seed,
+            limit,
+            iterations,
+            params,
+            tempering: None,
+            portfolio: None,
+            memetic: None,
+            tabu: None,
+            decompose: None,
+            init: Init::Unit,
+            adjacency: false,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        self.params.validate()?;
+        if let Some(tempering) = &self.tempering {
+            tempering.validate()?;
+        }
+        if let Some(portfolio) = &self.portfolio {
+            portfolio.validate()?;
+        }
+        if let Some(memetic) = &self.memetic {
+            memetic.validate()?;
+        }
+        if let Some(tabu) = &self.tabu {
+            tabu.validate()?;
+        }
+        if let Some(decompose) = &self.decompose {
+            decompose.validate()?;
+        }
+        let modes = [
+            self.tempering.is_some(),
+            self.portfolio.is_some(),
+            self.memetic.is_some(),
+            self.tabu.is_some(),
+            self.decompose.is_some(),
+        ];
+        if modes.iter().filter(|&&m| m).count() > 1 {
+            return Err(ParamsError::Invalid(
+                "only one of tempering, portfolio, memetic, tabu and decompose can be used"
+                    .to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn budget(&self) -> Budget {
+        match self.iterations {
+            Some(n) => Budget::Iterations(n),
+            None => Budget::Time(Duration::from_millis(self.limit)),
+        }
+    }
+
+    #[cfg(feature = "learn")]
+    pub fn load(path: &str) -> Result<RunConfig, ParamsError> {
+        let json = std::fs::read_to_string(path).map_err(|e| ParamsError::Io {
+            path: path.to_owned(),
+            source: e,
+        })?;
+        let record: RunRecord = serde_json::from_str(&json).map_err(|e| ParamsError::Json {
+            origin: path.to_owned(),
+            source: e,
+        })?;
+        record.config.validate()?;
+        Ok(record.config)
+    }
+
+    #[cfg(not(feature = "learn"))]
+    pub fn load(_path: &str) -> Result<RunConfig, ParamsError> {
+        Err(ParamsError::Invalid(
+            "loading a run config requires the `learn` feature".to_owned(),
+        ))
+    }
+
+    /// 結果と一緒に JSON で書き出す
+    /// `load` で読み込めば同じ設定で再実行できる
+    #[cfg(feature = "learn")]
+    pub fn save(&self, path: &str, n: usize, score: f64) -> std::io::Result<()> {
+        let record = RunRecord {
+            n,
+            score,
+            config: self.clone(),
+        };
+        let f = std::io::BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer_pretty(f, &record)?;
+        Ok(())
+    }
+
+    /// `io::Error::other` は提出先の Rust 1.42 にないので、`load` と同じ ParamsError で返す
+    #[cfg(not(feature = "learn"))]
+    pub fn save(&self, _path: &str, _n: usize, _score: f64) -> Result<(), ParamsError> {
+        Err(ParamsError::Invalid(
+            "saving a run config requires the `learn` feature".to_owned(),
+        ))
+    }
+}
+
+/// `RunConfig::save` で書き出す内容
+#[cfg(feature = "learn")]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RunRecord {
+    pub n: usize,
+    pub score: f64,
+    pub config: RunConfig,
+}
+
+pub fn run_with_stats(input: &Input, config: &RunConfig) -> (f64, Vec<Rect>, Stats) {
+    run_with_rng::<DefaultRng>(input, config)
+}
+
+/// 乱数生成器を R にして解く
+pub fn run_with_rng<R: SeedRng + Send + 'static>(
+    input: &Input,
+    config: &RunConfig,
+) -> (f64, Vec<Rect>, Stats) {
+    let start;
+    let input = match config.init {
+        Init::Unit => input,
+        init => {
+            start = Input {
+                rects: init.rects(input),
+                points: input.points.clone(),
+                sizes: input.sizes.clone(),
+            };
+            &start
+        }
+    };
+    if let Some(tempering) = &config.tempering {
+        return self::tempering::<R>(
+            config.seed,
+            &config.params,
+            input,
+            config.budget(),
+            tempering,
+        );
+    }
+    if let Some(memetic) = &config.memetic {
+        return self::memetic::<R>(config.seed, &config.params, input, config.budget(), memetic);
+    }
+    if let Some(tabu) = &config.tabu {
+        return self::tabu(input, config.budget(), tabu);
+    }
+    if let Some(decompose) = &config.decompose {
+        return self::decompose::<R>(
+            config.seed,
+            &config.params,
+            input,
+            config.budget(),
+            decompose,
+        );
+    }
+    match &config.portfolio {
+        Some(portfolio) => self::portfolio::<R>(
+            config.seed,
+            &config.params,
+            input,
+            config.budget(),
+            portfolio,
+        ),
+        None => {
+            let mut rng = R::seeded(config.seed as u128);
+            let mut annealer = Annealer::new(&config.params, input);
+            if config.adjacency {
+                annealer.state.track_adjacency();
+            }
+            anneal(&mut rng, &config.params, input, config.budget(), annealer)
+        }
+    }
+}
+
+pub fn run_with_config(input: &Input, config: &RunConfig) -> (f64, Vec<Rect>) {
+    let (score, best, _) = run_with_stats(input, config);
+    (score, best)
+}
+
+/// AtCoder のジャッジで tools/in/0001.txt を解いたときの1秒あたりの提案数
+/// README の「実行速度」の AtCoder コードテストの値
+pub const ATCODER_RATE: f64 = 15_298_000.0 / 1.006;
+
+/// AtCoder で limit ミリ秒回したときと同じくらいの提案回数
+pub fn atcoder_iterations(limit: u64) -> u64 {
+    (ATCODER_RATE * limit as f64 / 1000.0) as u64
+}
+
+/// millis ミリ秒 mc を回して、この環境での1秒あたりの提案数を測る
+pub fn calibrate(input: &Input, params: &McParams, millis: u64) -> f64 {
+    let mut rng = DefaultRng::seeded(1);
+    let now = Instant::now();
+    let (_, _, stats) = mc(
+        &mut rng,
+        params,
+        input,
+        Budget::Time(Duration::from_millis(millis)),
+    );
+    stats.all as f64 / now.elapsed().as_secs_f64()
+}
+
+pub fn run(input: Input, arg: Option<String>) -> Result<(f64, Vec<Rect>), ParamsError> {
+    let config = RunConfig::new(arg, input.rects.len())?;
+    Ok(run_with_config(&input, &config))
 }
 
 #[cfg(test)]
@@ -583,4 +1398,188 @@ mod tests {
         assert_eq!(children_gid_range(1), 5..9);
         assert_eq!(children_gid_range(2), 9..13);
     }
+
+    #[test]
+    fn test_qtree_intersect() {
+        let mut rng = Mcg128Xsl64::new(1);
+        let random_rect = |rng: &mut Mcg128Xsl64, max: i16| {
+            let x1 = rng.gen_range(0, L - 1);
+            let y1 = rng.gen_range(0, L - 1);
+            let x2 = rng.gen_range(x1 + 1, (x1 + max).min(L) + 1);
+            let y2 = rng.gen_range(y1 + 1, (y1 + max).min(L) + 1);
+            Rect::new(x1, x2, y1, y2)
+        };
+        // 重なりは気にせず、更新と判定が全部調べたときと一致するかだけ見る
+        let mut rects = (0..100)
+            .map(|_| random_rect(&mut rng, 2000))
+            .collect::<Vec<_>>();
+        let mut qtree = QTree::new(&rects);
+        for _ in 0..10000 {
+            let i = rng.gen_range(0, rects.len());
+            let new = random_rect(&mut rng, 2000);
+            qtree.update(&new, &rects[i], i);
+            rects[i] = new;
+            let grow = random_rect(&mut rng, 300);
+            assert_eq!(qtree.intersect(&grow, &rects), intersect(&grow, &rects));
+            let mut found = Vec::new();
+            qtree.for_each_intersect(&grow, &rects, |j| found.push(j));
+            found.sort_unstable();
+            let expected = (0..rects.len())
+                .filter(|&j| grow.intersect(&rects[j]))
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected);
+        }
+    }
+
+    fn stagnation_input() -> Input {
+        let points = vec![(100, 100), (5000, 5000), (9000, 1000)];
+        Input {
+            rects: points
+                .iter()
+                .map(|&(x, y)| Rect::new(x, x + 1, y, y + 1))
+                .collect(),
+            points,
+            sizes: vec![1_000_000, 2_000_000, 500_000],
+        }
+    }
+
+    #[test]
+    fn test_stagnation() {
+        let input = stagnation_input();
+        for action in [
+            StagnationAction::Perturb { k: 1 },
+            StagnationAction::Compact {
+                toward: Toward::Low,
+            },
+        ] {
+            let mut params = DEFAULT_PARAMS;
+            params.stagnation = Some(Stagnation {
+                window: 10_000,
+                action,
+            });
+            let mut rng = Mcg128Xsl64::new(1);
+            let mut annealer = Annealer::new(&params, &input);
+            let budget = Budget::Iterations(400_000);
+            while let Some(t) = progress(budget, Instant::now(), annealer.stats.all) {
+                annealer.step(&mut rng, &params, &input, t);
+            }
+            // 差分で追っているスコアが作り直したものと合う
+            let state = &annealer.state;
+            assert!((calc_score(&state.rects, &input.sizes).0 - state.score).abs() < 1e-9);
+            for (i, rect) in state.rects.iter().enumerate() {
+                assert!(rect.contain(input.points[i].0, input.points[i].1));
+                assert!(!intersect(rect, &state.rects[i + 1..]));
+            }
+
+            let (score, best, stats) = annealer.finish();
+            assert!(!stats.stagnations.is_empty());
+            assert!(stats.stagnations.iter().all(|e| e.score <= e.best_score));
+            assert!((calc_score(&best, &input.sizes).0 / 3.0 - score).abs() < 1e-9);
+            for (i, rect) in best.iter().enumerate() {
+                assert!(rect.contain(input.points[i].0, input.points[i].1));
+                assert!(!intersect(rect, &best[i + 1..]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_stagnation_reheat() {
+        let input = stagnation_input();
+        let mut params = DEFAULT_PARAMS;
+        let action = StagnationAction::Reheat { fraction: 0.5 };
+        params.stagnation = Some(Stagnation {
+            window: 10_000,
+            action: action.clone(),
+        });
+        let mut rng = Mcg128Xsl64::new(1);
+        let mut annealer = Annealer::new(&params, &input);
+        let t = 0.9;
+        for _ in 0..3 {
+            annealer.step(&mut rng, &params, &input, t);
+        }
+        let boost = (params.temp0 * 0.5 / temperature(&params, t)).ln();
+        annealer.stagnate(&action, &params, &input, t);
+        let start = annealer.stats.all;
+        assert_eq!(annealer.reheat, Some((start, boost)));
+        assert_eq!(annealer.improved_at, start);
+
+        // 次の停滞では温度の上乗せが新しい開始位置からやり直しになる
+        for _ in 0..3 {
+            annealer.step(&mut rng, &params, &input, t);
+        }
+        annealer.stagnate(&action, &params, &input, t);
+        let restart = annealer.stats.all;
+        assert!(restart > start);
+        assert_eq!(annealer.reheat, Some((restart, boost)));
+
+        // window だけ改善が続けば上乗せはなくなる
+        while annealer.stats.all - restart < 10_000 {
+            annealer.improved_at = annealer.stats.all;
+            annealer.step(&mut rng, &params, &input, t);
+        }
+        annealer.improved_at = annealer.stats.all;
+        annealer.step(&mut rng, &params, &input, t);
+        assert_eq!(annealer.reheat, None);
+        assert_eq!(annealer.stats.stagnations.len(), 2);
+    }
+
+    #[test]
+    fn test_sweep_presample() {
+        let input = stagnation_input();
+        let moves = Moves::new(&DEFAULT_PARAMS, 0.5, &BOARD);
+        let mut rng = Mcg128Xsl64::new(1);
+        let mut state = State::new(input.rects.to_vec(), &input.sizes);
+        let mut stats = Stats::default();
+        let mut criterion = FastMetropolis::new(1e-4);
+        for _ in 0..20 {
+            sweep_with(
+                &mut criterion,
+                &mut rng,
+                &mut state,
+                &moves,
+                &input,
+                1e-4,
+                &mut stats,
+            );
+            assert!((calc_score(&state.rects, &input.sizes).0 - state.score).abs() < 1e-9);
+        }
+        // 低温なので悪化する提案の多くは点や重なりを調べる前に下限で棄却される
+        assert!(stats.valid < stats.tried / 2);
+        assert!(stats.ac > 0);
+        for (i, rect) in state.rects.iter().enumerate() {
+            assert!(rect.contain(input.points[i].0, input.points[i].1));
+            assert!(!intersect(rect, &state.rects[i + 1..]));
+        }
+    }
+
+    #[test]
+    fn test_stagnation_restart() {
+        let input = stagnation_input();
+        let params = DEFAULT_PARAMS;
+        let mut rng = Mcg128Xsl64::new(1);
+        let mut annealer = Annealer::new(&params, &input);
+        // 高温で回して、今の解を最良解から離す
+        for _ in 0..50 {
+            annealer.step(&mut rng, &params, &input, 0.0);
+            if annealer.state.score < annealer.state.best_score {
+                break;
+            }
+        }
+        assert!(annealer.state.score < annealer.state.best_score);
+        let coords = |rects: &[Rect]| {
+            rects
+                .iter()
+                .map(|r| (r.x1, r.x2, r.y1, r.y2))
+                .collect::<Vec<_>>()
+        };
+        let best = coords(&annealer.state.best());
+        assert_ne!(coords(&annealer.state.rects), best);
+
+        annealer.stagnate(&StagnationAction::Restart, &params, &input, 0.0);
+        let state = &annealer.state;
+        assert_eq!(coords(&state.rects), best);
+        assert!((state.score - state.best_score).abs() < 1e-9);
+        assert!((calc_score(&state.rects, &input.sizes).0 - state.score).abs() < 1e-9);
+        assert_eq!(coords(&state.best()), best);
+    }
 }
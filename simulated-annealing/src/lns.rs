@@ -0,0 +1,202 @@
+use rand::{
+    distributions::{Distribution, Uniform},
+    Rng, RngCore,
+};
+
+use super::{params::Lns, Criterion, Input, Moves, Rect, State, Stats};
+
+/// 窓の中に点がある長方形を 1x1 に戻し、貪欲に広げてから窓の中だけで焼きなます
+/// 焼きなましの受理判定は温度 temp の criterion で行う
+/// スコアが上がれば採用し、そうでなければ元に戻す
+/// 採用したら true
+#[allow(clippy::too_many_arguments)]
+pub(super) fn destroy_and_repair<R: RngCore, C: Criterion>(
+    rng: &mut R,
+    state: &mut State,
+    moves: &Moves,
+    input: &Input,
+    lns: &Lns,
+    criterion: &mut C,
+    temp: f64,
+    stats: &mut Stats,
+) -> bool {
+    let n = state.rects.len();
+    let (cx, cy) = input.points[rng.gen_range(0, n)];
+    // 窓は動かせる範囲に収める
+    let b = &state.bounds;
+    let x1 = (cx - lns.size / 2).min(b.x2 - lns.size).max(b.x1);
+    let y1 = (cy - lns.size / 2).min(b.y2 - lns.size).max(b.y1);
+    let window = Rect::new(x1, (x1 + lns.size).min(b.x2), y1, (y1 + lns.size).min(b.y2));
+    let mut members = (0..n)
+        .filter(|&i| window.contain(input.points[i].0, input.points[i].1))
+        .collect::<Vec<_>>();
+    stats.lns.0 += 1;
+
+    let before = state.score;
+    state.begin();
+    for &i in members.iter() {
+        let (x, y) = input.points[i];
+        state.set(i, Rect::new(x, x + 1, y, y + 1), &input.sizes);
+    }
+    // 大きいものから広げる
+    members.sort_by_key(|&i| -input.sizes[i]);
+    for &i in members.iter() {
+        grow(state, i, &input.sizes);
+    }
+
+    criterion.prepare(temp, state.score, state.best_score);
+    let member_sample = Uniform::new(0, members.len());
+    for _ in 0..lns.repair {
+        let i = members[member_sample.sample(rng)];
+        let rect = &state.rects[i];
+        let new = match moves.propose(rng, rect) {
+            Some(new) if new.contain(input.points[i].0, input.points[i].1) => new,
+            _ => continue,
+        };
+        let score_diff = new.score(input.sizes[i]) - state.scores[i];
+        if !criterion.accept(rng, score_diff, state.score) {
+            continue;
+        }
+        if let Some(grow) = rect.grow_rect(&new) {
+            if state.qtree.intersect(&grow, &state.rects) {
+                continue;
+            }
+        }
+        state.set(i, new, &input.sizes);
+    }
+    stats.all += lns.repair;
+
+    if state.score > before {
+        state.commit();
+        stats.lns.1 += 1;
+        if state.score > state.best_score {
+            state.update_best();
+        }
+        true
+    } else {
+        state.rollback(&input.sizes);
+        false
+    }
+}
+
+/// 重ならず面積が r を超えない範囲で、4方向に交互に広げる
+fn grow(state: &mut State, i: usize, sizes: &[i32]) {
+    let r = sizes[i];
+    let side = (r as f64).sqrt() as i32;
+    loop {
+        let mut changed = false;
+        for dir in 0..4 {
+            let rect = &state.rects[i];
+            let (w, h) = ((rect.x2 - rect.x1) as i32, (rect.y2 - rect.y1) as i32);
+            let (len, other) = if dir < 2 { (w, h) } else { (h, w) };
+            let b = &state.bounds;
+            let room = match dir {
+                0 => rect.x1 - b.x1,
+                1 => b.x2 - rect.x2,
+                2 => rect.y1 - b.y1,
+                _ => b.y2 - rect.y2,
+            } as i32;
+            let limit = r / other - len;
+            // 正方形より短い間は半分ずつ、その後は面積の上限まで
+            let cap = if len < side {
+                ((side - len + 1) / 2).max(1)
+            } else {
+                limit
+            };
+            let max = cap.min(room).min(limit);
+            if max <= 0 {
+                continue;
+            }
+            let extend = |d: i32| {
+                let d = d as i16;
+                match dir {
+                    0 => Rect::new(rect.x1 - d, rect.x1, rect.y1, rect.y2),
+                    1 => Rect::new(rect.x2, rect.x2 + d, rect.y1, rect.y2),
+                    2 => Rect::new(rect.x1, rect.x2, rect.y1 - d, rect.y1),
+                    _ => Rect::new(rect.x1, rect.x2, rect.y2, rect.y2 + d),
+                }
+            };
+            // 重ならない最大の幅を二分探索
+            let (mut ok, mut ng) = (0, max + 1);
+            while ng - ok > 1 {
+                let mid = (ok + ng) / 2;
+                if state.qtree.intersect(&extend(mid), &state.rects) {
+                    ng = mid;
+                } else {
+                    ok = mid;
+                }
+            }
+            if ok == 0 {
+                continue;
+            }
+            let d = ok as i16;
+            let new = match dir {
+                0 => Rect::new(rect.x1 - d, rect.x2, rect.y1, rect.y2),
+                1 => Rect::new(rect.x1, rect.x2 + d, rect.y1, rect.y2),
+                2 => Rect::new(rect.x1, rect.x2, rect.y1 - d, rect.y2),
+                _ => Rect::new(rect.x1, rect.x2, rect.y1, rect.y2 + d),
+            };
+            state.set(i, new, sizes);
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proconio::source::once::OnceSource;
+
+    use rand_pcg::Mcg128Xsl64;
+
+    use super::super::{
+        calc_score, guillotine, parse_source, Acceptance, McParams, BOARD, DEFAULT_PARAMS,
+    };
+    use super::*;
+
+    #[test]
+    fn test_destroy_and_repair() {
+        let input = parse_source(OnceSource::new(
+            include_str!("../../tools/in/0000.txt").as_bytes(),
+        ));
+        let initial = guillotine(&input.points, &input.sizes);
+        let start = State::new(initial.clone(), &input.sizes).score;
+        let params: McParams = DEFAULT_PARAMS;
+        let moves = Moves::new(&params, 0.5, &BOARD);
+        let lns = Lns {
+            interval: 2000,
+            size: 2500,
+            repair: 2000,
+        };
+        for acceptance in [Acceptance::Metropolis, Acceptance::Threshold].iter() {
+            let mut state = State::new(initial.clone(), &input.sizes);
+            let mut criterion = acceptance.criterion(input.rects.len());
+            let mut rng = Mcg128Xsl64::new(1);
+            let mut stats = Stats::default();
+            for _ in 0..50 {
+                destroy_and_repair(
+                    &mut rng,
+                    &mut state,
+                    &moves,
+                    &input,
+                    &lns,
+                    &mut criterion,
+                    1e-3,
+                    &mut stats,
+                );
+                let s = calc_score(&state.rects, &input.sizes).0;
+                assert!((state.score - s).abs() < 1e-6);
+            }
+            assert_eq!(stats.lns.0, 50);
+            assert!(stats.lns.1 > 0);
+            assert!(state.score > start);
+            let rects = &state.rects;
+            for (i, rect) in rects.iter().enumerate() {
+                assert!(rect.contain(input.points[i].0, input.points[i].1));
+                assert!(rects[i + 1..].iter().all(|other| !rect.intersect(other)));
+            }
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::time::Instant;
+
+use rand::{Rng, RngCore};
+#[cfg(feature = "learn")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    params::{McParams, ParamsError},
+    progress, Annealer, Budget, Input, Rect, SeedRng, Stats, L,
+};
+
+/// 集団で解く設定
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Memetic {
+    /// 個体数
+    pub population: usize,
+    /// 初期個体を作る焼きなましの提案数（t = 0 から 1 まで）
+    pub init: u64,
+    /// 子の局所探索の提案数（t = local_start から 1 まで）
+    pub local: u64,
+    pub local_start: f64,
+}
+
+impl Memetic {
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        if self.population < 2 {
+            return Err(ParamsError::Invalid(
+                "memetic population must be at least 2".to_owned(),
+            ));
+        }
+        // 0 だと Budget::Iterations で提案数が増えず、終わらなくなる
+        if self.init == 0 || self.local == 0 {
+            return Err(ParamsError::Invalid(format!(
+                "memetic init and local must be positive (got {} and {})",
+                self.init, self.local
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.local_start) {
+            return Err(ParamsError::Invalid(format!(
+                "memetic local_start must be in [0, 1] (got {})",
+                self.local_start
+            )));
+        }
+        Ok(())
+    }
+}
+
+struct Individual {
+    score: f64,
+    rects: Vec<Rect>,
+}
+
+/// rects から count 回、t を t0 から 1 まで進めて焼きなます
+/// 全体の予算が尽きたら途中でやめる
+#[allow(clippy::too_many_arguments)]
+fn anneal<R: RngCore>(
+    rng: &mut R,
+    params: &McParams,
+    input: &Input,
+    rects: Vec<Rect>,
+    t0: f64,
+    count: u64,
+    budget: Budget,
+    now: Instant,
+    stats: &mut Stats,
+) -> Individual {
+    let mut annealer = Annealer::from_rects(params, rects, &input.sizes);
+    while annealer.stats.all < count
+        && progress(budget, now, stats.all + annealer.stats.all).is_some()
+    {
+        let t = t0 + (1.0 - t0) * annealer.stats.all as f64 / count as f64;
+        annealer.step(rng, params, input, t);
+    }
+    let (score, rects, s) = annealer.finish();
+    stats.all += s.all;
+    stats.tried += s.tried;
+    stats.valid += s.valid;
+    stats.ac += s.ac;
+    Individual { score, rects }
+}
+
+/// 窓の中に点がある長方形を a から、残りを b から取る
+/// b から取ったものは、すでに置いたものと重ならないよう点を含んだまま削る
+fn crossover(input: &Input, a: &[Rect], b: &[Rect], window: &Rect) -> Vec<Rect> {
+    let n = a.len();
+    let inside = (0..n)
+        .map(|i| window.contain(input.points[i].0, input.points[i].1))
+        .collect::<Vec<_>>();
+    let mut child = (0..n)
+        .map(|i| {
+            if inside[i] {
+                a[i].clone()
+            } else {
+                b[i].clone()
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut placed = (0..n).filter(|&i| inside[i]).collect::<Vec<_>>();
+    for j in (0..n).filter(|&j| !inside[j]) {
+        let (x, y) = input.points[j];
+        // a 同士、b 同士は重ならず、どの長方形も他の点のマスを含まないので、
+        // 重なる相手ごとに点を残して切り離す方法が必ずある
+        while let Some(&k) = placed.iter().find(|&&k| child[k].intersect(&child[j])) {
+            let (rect, other) = (&child[j], &child[k]);
+            let side = |ok: bool, r: Rect| if ok { Some(r) } else { None };
+            let candidates = [
+                side(x < other.x1, Rect::new(rect.x1, other.x1, rect.y1, rect.y2)),
+                side(
+                    other.x2 <= x,
+                    Rect::new(other.x2, rect.x2, rect.y1, rect.y2),
+                ),
+                side(y < other.y1, Rect::new(rect.x1, rect.x2, rect.y1, other.y1)),
+                side(
+                    other.y2 <= y,
+                    Rect::new(rect.x1, rect.x2, other.y2, rect.y2),
+                ),
+            ];
+            child[j] = candidates
+                .iter()
+                .flatten()
+                .max_by_key(|r| r.size())
+                .cloned()
+                .unwrap_or_else(|| Rect::new(x, x + 1, y, y + 1));
+        }
+        placed.push(j);
+    }
+    child
+}
+
+/// 集団を使う解法（memetic algorithm）
+///
+/// 初期個体をそれぞれ init 回焼きなまして作り、予算が尽きるまで
+/// 2回のトーナメントで親を選ぶ → 盤面の窓で交叉 → 子を local 回焼きなます → 最悪の個体と入れ替える
+/// を繰り返す。budget は全体の量。
+/// Stats は全焼きなましの合計。
+pub fn memetic<R: SeedRng>(
+    seed: u64,
+    params: &McParams,
+    input: &Input,
+    budget: Budget,
+    config: &Memetic,
+) -> (f64, Vec<Rect>, Stats) {
+    let now = Instant::now();
+    let mut rng = R::seeded(seed as u128);
+    let mut stats = Stats::default();
+    let mut population = Vec::with_capacity(config.population);
+    for _ in 0..config.population {
+        let rects = input.rects.clone();
+        let individual = anneal(
+            &mut rng,
+            params,
+            input,
+            rects,
+            0.0,
+            config.init,
+            budget,
+            now,
+            &mut stats,
+        );
+        population.push(individual);
+    }
+
+    let tournament = |rng: &mut R, population: &[Individual]| {
+        let i = rng.gen_range(0, population.len());
+        let j = rng.gen_range(0, population.len());
+        if population[i].score >= population[j].score {
+            i
+        } else {
+            j
+        }
+    };
+    while progress(budget, now, stats.all).is_some() {
+        let a = tournament(&mut rng, &population);
+        let b = tournament(&mut rng, &population);
+        if a == b {
+            continue;
+        }
+        let w = rng.gen_range(L / 4, L / 2 + 1);
+        let h = rng.gen_range(L / 4, L / 2 + 1);
+        let x1 = rng.gen_range(0, L - w + 1);
+        let y1 = rng.gen_range(0, L - h + 1);
+        let window = Rect::new(x1, x1 + w, y1, y1 + h);
+        let child = crossover(input, &population[a].rects, &population[b].rects, &window);
+        let child = anneal(
+            &mut rng,
+            params,
+            input,
+            child,
+            config.local_start,
+            config.local,
+            budget,
+            now,
+            &mut stats,
+        );
+
+        let worst = (0..population.len())
+            .min_by(|&i, &j| {
+                population[i]
+                    .score
+                    .partial_cmp(&population[j].score)
+                    .unwrap()
+            })
+            .unwrap();
+        // 同じスコアの個体は入れない（集団が1つの解で埋まるのを防ぐ）
+        if child.score > population[worst].score
+            && population.iter().all(|p| p.score != child.score)
+        {
+            population[worst] = child;
+        }
+    }
+
+    let best = population
+        .into_iter()
+        .max_by(|a, b| a.score.partial_cmp(&b.score).unwrap())
+        .unwrap();
+    (best.score, best.rects, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use proconio::source::once::OnceSource;
+
+    use super::super::{guillotine, parse_source};
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let memetic = Memetic {
+            population: 4,
+            init: 10_000,
+            local: 2_000,
+            local_start: 0.5,
+        };
+        assert!(memetic.validate().is_ok());
+        assert!(Memetic {
+            local: 0,
+            ..memetic.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(Memetic {
+            init: 0,
+            ..memetic.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(Memetic {
+            population: 1,
+            ..memetic
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_crossover() {
+        let input = parse_source(OnceSource::new(
+            include_str!("../../tools/in/0000.txt").as_bytes(),
+        ));
+        let a = guillotine(&input.points, &input.sizes);
+        // 転置した点で作れば a とは大きく違う配置になる
+        let transposed = input
+            .points
+            .iter()
+            .map(|&(x, y)| (y, x))
+            .collect::<Vec<_>>();
+        let b = guillotine(&transposed, &input.sizes)
+            .into_iter()
+            .map(|r| Rect::new(r.y1, r.y2, r.x1, r.x2))
+            .collect::<Vec<_>>();
+        let window = Rect::new(2000, 7000, 3000, 6000);
+        let child = crossover(&input, &a, &b, &window);
+        for (i, rect) in child.iter().enumerate() {
+            let (x, y) = input.points[i];
+            assert!(rect.contain(x, y));
+            if window.contain(x, y) {
+                assert_eq!(
+                    (rect.x1, rect.x2, rect.y1, rect.y2),
+                    (a[i].x1, a[i].x2, a[i].y1, a[i].y2)
+                );
+            }
+            assert!(child[i + 1..].iter().all(|other| !rect.intersect(other)));
+        }
+    }
+}
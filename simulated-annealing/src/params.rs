@@ -0,0 +1,709 @@
+use std::fmt;
+
+#[cfg(feature = "learn")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "learn")]
+use super::table::{ParamsTable, PARAMS_TABLE_ENV};
+use super::{acceptance::Acceptance, compact::Toward, schedule::Schedule, table::PARAMS_TABLE, L};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(try_from = "McParamsRepr")
+)]
+pub struct McParams {
+    pub(crate) temp0: f64,
+    pub(crate) temp1: f64,
+    pub(crate) slide_d: Schedule,
+    pub(crate) grow_d1: Schedule,
+    pub(crate) grow_d2: Schedule,
+    pub(crate) grow_d3: Schedule,
+    pub(crate) weight_slide: Schedule,
+    pub(crate) weight_d1: Schedule,
+    pub(crate) weight_d2: Schedule,
+    pub(crate) weight_d3: Schedule,
+    #[cfg_attr(feature = "learn", serde(skip_serializing_if = "Option::is_none"))]
+    pub(crate) stagnation: Option<Stagnation>,
+    #[cfg_attr(feature = "learn", serde(skip_serializing_if = "Option::is_none"))]
+    pub(crate) lns: Option<Lns>,
+    #[cfg_attr(
+        feature = "learn",
+        serde(skip_serializing_if = "Acceptance::is_metropolis")
+    )]
+    pub(crate) acceptance: Acceptance,
+}
+
+/// 停滞したとみなす条件と、そのときの動作
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Stagnation {
+    /// 最良スコアがこの提案回数のあいだ更新されなければ停滞とみなす
+    pub window: u64,
+    pub action: StagnationAction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum StagnationAction {
+    /// 温度を temp0 * fraction まで上げ、window 回かけて元のスケジュールに戻す
+    Reheat { fraction: f64 },
+    /// 最良解から再開する
+    Restart,
+    /// スコアの低い k 個の長方形を 1x1 に戻す
+    Perturb { k: usize },
+    /// 今の配置を toward へ寄せて隙間をまとめる
+    Compact { toward: Toward },
+}
+
+/// 盤面の一部を壊して作り直す近傍（LNS）
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Lns {
+    /// この提案回数ごとに1回行う（2000 単位に切り上げ）
+    pub interval: u64,
+    /// 壊す窓の一辺
+    pub size: i16,
+    /// 作り直した後に窓の中の長方形だけで焼きなます提案回数
+    pub repair: u64,
+}
+
+pub const DEFAULT_PARAMS: McParams = McParams {
+    temp0: 0.10868564634648839,
+    temp1: 0.00029342425784192465,
+    slide_d: Schedule::constant(59.0),
+    grow_d1: Schedule::Linear {
+        start: 661.4780032749206,
+        end: 7.211273402804876,
+    },
+    grow_d2: Schedule::Linear {
+        start: 1532.4395218778254,
+        end: 3.178973798285788,
+    },
+    grow_d3: Schedule::Linear {
+        start: 660.2448737846898,
+        end: 5.668514832161116,
+    },
+    weight_slide: Schedule::constant(0.056353565358393205),
+    weight_d1: Schedule::constant(0.1745393427756753),
+    weight_d2: Schedule::constant(0.7691070918659315),
+    weight_d3: Schedule::constant(0.0),
+    stagnation: None,
+    lns: None,
+    acceptance: Acceptance::Metropolis,
+};
+
+#[derive(Debug)]
+pub enum ParamsError {
+    #[cfg(feature = "learn")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[cfg(feature = "learn")]
+    Json {
+        origin: String,
+        source: serde_json::Error,
+    },
+    Invalid(String),
+}
+
+impl fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "learn")]
+            ParamsError::Io { path, source } => {
+                write!(f, "cannot read params {}: {}", path, source)
+            }
+            #[cfg(feature = "learn")]
+            ParamsError::Json { origin, source } => {
+                write!(f, "cannot parse params from {}: {}", origin, source)
+            }
+            ParamsError::Invalid(msg) => write!(f, "invalid params: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ParamsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "learn")]
+            ParamsError::Io { source, .. } => Some(source),
+            #[cfg(feature = "learn")]
+            ParamsError::Json { source, .. } => Some(source),
+            ParamsError::Invalid(_) => None,
+        }
+    }
+}
+
+fn invalid<T>(msg: String) -> Result<T, ParamsError> {
+    Err(ParamsError::Invalid(msg))
+}
+
+impl McParams {
+    fn step_schedules(&self) -> [(&'static str, &Schedule); 4] {
+        [
+            ("slide_d", &self.slide_d),
+            ("grow_d1", &self.grow_d1),
+            ("grow_d2", &self.grow_d2),
+            ("grow_d3", &self.grow_d3),
+        ]
+    }
+
+    fn weight_schedules(&self) -> [(&'static str, &Schedule); 4] {
+        [
+            ("weight_slide", &self.weight_slide),
+            ("weight_d1", &self.weight_d1),
+            ("weight_d2", &self.weight_d2),
+            ("weight_d3", &self.weight_d3),
+        ]
+    }
+
+    /// mc が正しく動く範囲のパラメータかチェック
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        if !(self.temp0.is_finite() && self.temp0 > 0.0) {
+            return invalid(format!("temp0 must be positive (got {})", self.temp0));
+        }
+        if !(self.temp1.is_finite() && self.temp1 > 0.0) {
+            return invalid(format!("temp1 must be positive (got {})", self.temp1));
+        }
+        if self.temp0 < self.temp1 {
+            return invalid(format!(
+                "temp0 must be >= temp1 (got temp0={}, temp1={})",
+                self.temp0, self.temp1
+            ));
+        }
+        for (name, schedule) in self.step_schedules().iter() {
+            schedule
+                .check()
+                .or_else(|e| invalid(format!("{}: {}", name, e)))?;
+            let (lo, hi) = schedule.bounds();
+            if lo < 0.0 || hi > L as f64 {
+                return invalid(format!(
+                    "{} must be in [0, {}] (got [{}, {}])",
+                    name, L, lo, hi
+                ));
+            }
+        }
+        let weights = self.weight_schedules();
+        // 各スケジュールは端点と折れ線の節点の間で単調なので、そこだけ見れば十分
+        let mut ts = vec![0.0, 1.0];
+        for (name, schedule) in weights.iter() {
+            schedule
+                .check()
+                .or_else(|e| invalid(format!("{}: {}", name, e)))?;
+            let (lo, _) = schedule.bounds();
+            if lo < 0.0 {
+                return invalid(format!("{} must be non-negative (got {})", name, lo));
+            }
+            if let Schedule::Piecewise { knots } = schedule {
+                ts.extend(
+                    knots
+                        .iter()
+                        .map(|&(t, _)| t)
+                        .filter(|t| 0.0 < *t && *t < 1.0),
+                );
+            }
+        }
+        for &t in ts.iter() {
+            let sum: f64 = weights.iter().map(|(_, s)| s.at(t)).sum();
+            if sum <= 0.0 {
+                return invalid(format!("sum of weights must be positive (zero at t={})", t));
+            }
+        }
+        if let Some(stagnation) = &self.stagnation {
+            if stagnation.window == 0 {
+                return invalid("stagnation window must be positive".to_owned());
+            }
+            match stagnation.action {
+                StagnationAction::Reheat { fraction } if !(0.0 < fraction && fraction <= 1.0) => {
+                    return invalid(format!(
+                        "reheat fraction must be in (0, 1] (got {})",
+                        fraction
+                    ));
+                }
+                StagnationAction::Perturb { k: 0 } => {
+                    return invalid("perturb k must be positive".to_owned());
+                }
+                _ => {}
+            }
+        }
+        self.acceptance.check().map_err(ParamsError::Invalid)?;
+        if let Some(lns) = &self.lns {
+            if lns.interval == 0 {
+                return invalid("lns interval must be positive".to_owned());
+            }
+            if !(0 < lns.size && lns.size <= L) {
+                return invalid(format!("lns size must be in [1, {}] (got {})", L, lns.size));
+            }
+        }
+        Ok(())
+    }
+
+    /// self と other を (1 - s) : s で混ぜる
+    /// 温度は対数スケールで補間する
+    pub fn lerp(&self, other: &McParams, s: f64) -> McParams {
+        McParams {
+            temp0: self.temp0.powf(1.0 - s) * other.temp0.powf(s),
+            temp1: self.temp1.powf(1.0 - s) * other.temp1.powf(s),
+            slide_d: self.slide_d.lerp(&other.slide_d, s),
+            grow_d1: self.grow_d1.lerp(&other.grow_d1, s),
+            grow_d2: self.grow_d2.lerp(&other.grow_d2, s),
+            grow_d3: self.grow_d3.lerp(&other.grow_d3, s),
+            weight_slide: self.weight_slide.lerp(&other.weight_slide, s),
+            weight_d1: self.weight_d1.lerp(&other.weight_d1, s),
+            weight_d2: self.weight_d2.lerp(&other.weight_d2, s),
+            weight_d3: self.weight_d3.lerp(&other.weight_d3, s),
+            stagnation: if s < 0.5 {
+                self.stagnation.clone()
+            } else {
+                other.stagnation.clone()
+            },
+            lns: if s < 0.5 {
+                self.lns.clone()
+            } else {
+                other.lns.clone()
+            },
+            acceptance: if s < 0.5 {
+                self.acceptance.clone()
+            } else {
+                other.acceptance.clone()
+            },
+        }
+    }
+}
+
+/// JSON 上の表現
+/// 与えられたフィールドだけを上書きする
+/// `slide_d: {"type": "log", ...}` のようなスケジュール指定と、
+/// 旧来の `slide_d_start`, `slide_d_end` の平坦な指定（線形）の両方を受け付ける
+#[cfg(feature = "learn")]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct McParamsRepr {
+    temp0: Option<f64>,
+    temp1: Option<f64>,
+    slide_d: Option<Schedule>,
+    slide_d_start: Option<f64>,
+    slide_d_end: Option<f64>,
+    grow_d1: Option<Schedule>,
+    grow_d1_start: Option<f64>,
+    grow_d1_end: Option<f64>,
+    grow_d2: Option<Schedule>,
+    grow_d2_start: Option<f64>,
+    grow_d2_end: Option<f64>,
+    grow_d3: Option<Schedule>,
+    grow_d3_start: Option<f64>,
+    grow_d3_end: Option<f64>,
+    weight_slide: Option<Schedule>,
+    weight_slide_start: Option<f64>,
+    weight_slide_end: Option<f64>,
+    weight_d1: Option<Schedule>,
+    weight_d1_start: Option<f64>,
+    weight_d1_end: Option<f64>,
+    weight_d2: Option<Schedule>,
+    weight_d2_start: Option<f64>,
+    weight_d2_end: Option<f64>,
+    weight_d3: Option<Schedule>,
+    weight_d3_start: Option<f64>,
+    weight_d3_end: Option<f64>,
+    /// null なら停滞検出をやめる
+    #[serde(default, deserialize_with = "some")]
+    stagnation: Option<Option<Stagnation>>,
+    /// null なら LNS をやめる
+    #[serde(default, deserialize_with = "some")]
+    lns: Option<Option<Lns>>,
+    acceptance: Option<Acceptance>,
+}
+
+/// フィールドがあれば（null でも）Some にする
+#[cfg(feature = "learn")]
+fn some<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[cfg(feature = "learn")]
+fn merge_schedule(
+    name: &str,
+    target: &mut Schedule,
+    schedule: Option<Schedule>,
+    start: Option<f64>,
+    end: Option<f64>,
+) -> Result<(), String> {
+    match (schedule, start, end) {
+        (None, None, None) => Ok(()),
+        (Some(schedule), None, None) => {
+            *target = schedule;
+            Ok(())
+        }
+        (Some(_), _, _) => Err(format!(
+            "`{0}` and `{0}_start`/`{0}_end` are mutually exclusive",
+            name
+        )),
+        (None, start, end) => {
+            let (base_start, base_end) = match *target {
+                Schedule::Linear { start, end } => (Some(start), Some(end)),
+                _ => (None, None),
+            };
+            match (start.or(base_start), end.or(base_end)) {
+                (Some(start), Some(end)) => {
+                    *target = Schedule::Linear { start, end };
+                    Ok(())
+                }
+                _ => Err(format!(
+                    "`{0}` is not linear, so both `{0}_start` and `{0}_end` are required",
+                    name
+                )),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "learn")]
+impl McParamsRepr {
+    fn merge_into(self, p: &mut McParams) -> Result<(), String> {
+        if let Some(temp0) = self.temp0 {
+            p.temp0 = temp0;
+        }
+        if let Some(temp1) = self.temp1 {
+            p.temp1 = temp1;
+        }
+        merge_schedule(
+            "slide_d",
+            &mut p.slide_d,
+            self.slide_d,
+            self.slide_d_start,
+            self.slide_d_end,
+        )?;
+        merge_schedule(
+            "grow_d1",
+            &mut p.grow_d1,
+            self.grow_d1,
+            self.grow_d1_start,
+            self.grow_d1_end,
+        )?;
+        merge_schedule(
+            "grow_d2",
+            &mut p.grow_d2,
+            self.grow_d2,
+            self.grow_d2_start,
+            self.grow_d2_end,
+        )?;
+        merge_schedule(
+            "grow_d3",
+            &mut p.grow_d3,
+            self.grow_d3,
+            self.grow_d3_start,
+            self.grow_d3_end,
+        )?;
+        merge_schedule(
+            "weight_slide",
+            &mut p.weight_slide,
+            self.weight_slide,
+            self.weight_slide_start,
+            self.weight_slide_end,
+        )?;
+        merge_schedule(
+            "weight_d1",
+            &mut p.weight_d1,
+            self.weight_d1,
+            self.weight_d1_start,
+            self.weight_d1_end,
+        )?;
+        merge_schedule(
+            "weight_d2",
+            &mut p.weight_d2,
+            self.weight_d2,
+            self.weight_d2_start,
+            self.weight_d2_end,
+        )?;
+        merge_schedule(
+            "weight_d3",
+            &mut p.weight_d3,
+            self.weight_d3,
+            self.weight_d3_start,
+            self.weight_d3_end,
+        )?;
+        if let Some(stagnation) = self.stagnation {
+            p.stagnation = stagnation;
+        }
+        if let Some(lns) = self.lns {
+            p.lns = lns;
+        }
+        if let Some(acceptance) = self.acceptance {
+            p.acceptance = acceptance;
+        }
+        Ok(())
+    }
+}
+
+/// デシリアライズは DEFAULT_PARAMS への上書きとして扱う
+#[cfg(feature = "learn")]
+impl std::convert::TryFrom<McParamsRepr> for McParams {
+    type Error = String;
+
+    fn try_from(r: McParamsRepr) -> Result<McParams, String> {
+        let mut params = DEFAULT_PARAMS;
+        r.merge_into(&mut params)?;
+        params.validate().map_err(|e| e.to_string())?;
+        Ok(params)
+    }
+}
+
+/// パラメータを上書きする環境変数
+#[cfg(feature = "learn")]
+pub const PARAMS_ENV: &str = "AHC001_PARAMS";
+
+#[cfg(feature = "learn")]
+impl McParams {
+    /// `source` は JSON 文字列か、`@` に続けてファイルパス
+    /// 書かれているフィールドだけを上書きする（検証はしない）
+    pub fn merge(&mut self, source: &str, origin: &str) -> Result<(), ParamsError> {
+        let (origin, json) = if source.starts_with('@') {
+            let path = &source[1..];
+            let json = std::fs::read_to_string(path).map_err(|e| ParamsError::Io {
+                path: path.to_owned(),
+                source: e,
+            })?;
+            (path.to_owned(), json)
+        } else {
+            (origin.to_owned(), source.to_owned())
+        };
+        let repr: McParamsRepr = serde_json::from_str(&json).map_err(|e| ParamsError::Json {
+            origin: origin.clone(),
+            source: e,
+        })?;
+        repr.merge_into(self)
+            .map_err(|e| ParamsError::Invalid(format!("{}: {}", origin, e)))
+    }
+
+    /// const に埋め込める Rust の式
+    pub fn to_rust(&self) -> String {
+        let fields = [
+            ("slide_d", &self.slide_d),
+            ("grow_d1", &self.grow_d1),
+            ("grow_d2", &self.grow_d2),
+            ("grow_d3", &self.grow_d3),
+            ("weight_slide", &self.weight_slide),
+            ("weight_d1", &self.weight_d1),
+            ("weight_d2", &self.weight_d2),
+            ("weight_d3", &self.weight_d3),
+        ];
+        let mut s = format!(
+            "super::params::McParams {{\n    temp0: {:?},\n    temp1: {:?},\n",
+            self.temp0, self.temp1
+        );
+        for (name, schedule) in fields.iter() {
+            s += &format!("    {}: {},\n", name, schedule.to_rust());
+        }
+        s += &format!(
+            "    stagnation: {},\n",
+            stagnation_to_rust(&self.stagnation)
+        );
+        s += &match &self.lns {
+            Some(lns) => format!(
+                "    lns: Some(super::params::Lns {{ interval: {}, size: {}, repair: {} }}),\n",
+                lns.interval, lns.size, lns.repair
+            ),
+            None => "    lns: None,\n".to_owned(),
+        };
+        s += &format!(
+            "    acceptance: super::acceptance::Acceptance::{:?},\n",
+            self.acceptance
+        );
+        s += "}";
+        s
+    }
+}
+
+#[cfg(feature = "learn")]
+fn stagnation_to_rust(stagnation: &Option<Stagnation>) -> String {
+    let stagnation = match stagnation {
+        Some(stagnation) => stagnation,
+        None => return "None".to_owned(),
+    };
+    let action = match stagnation.action {
+        StagnationAction::Reheat { fraction } => {
+            format!("Reheat {{ fraction: {:?} }}", fraction)
+        }
+        StagnationAction::Restart => "Restart".to_owned(),
+        StagnationAction::Perturb { k } => format!("Perturb {{ k: {} }}", k),
+        StagnationAction::Compact { toward } => {
+            format!("Compact {{ toward: super::compact::Toward::{:?} }}", toward)
+        }
+    };
+    format!(
+        "Some(super::params::Stagnation {{ window: {}, action: super::params::StagnationAction::{} }})",
+        stagnation.window, action
+    )
+}
+
+/// PARAMS_TABLE（環境変数 AHC001_PARAMS_TABLE でファイルを指定すればそれ）から n に合うものを選び、
+/// 環境変数 AHC001_PARAMS、引数の順で上書きして検証する
+#[cfg(feature = "learn")]
+pub fn get_params(arg: Option<String>, n: usize) -> Result<McParams, ParamsError> {
+    let mut params = match std::env::var(PARAMS_TABLE_ENV) {
+        Ok(path) => ParamsTable::load(&path)?.select(n),
+        Err(_) => PARAMS_TABLE.select(n),
+    };
+    if let Ok(env) = std::env::var(PARAMS_ENV) {
+        params.merge(&env, &format!("${}", PARAMS_ENV))?;
+    }
+    if let Some(arg) = arg {
+        params.merge(&arg, "argument")?;
+    }
+    params.validate()?;
+    Ok(params)
+}
+
+#[cfg(not(feature = "learn"))]
+pub fn get_params(_arg: Option<String>, n: usize) -> Result<McParams, ParamsError> {
+    Ok(PARAMS_TABLE.select(n))
+}
+
+#[cfg(all(test, feature = "learn"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_and_schedule_json() {
+        let flat = r#"{
+            "temp0": 0.1, "temp1": 0.001,
+            "slide_d_start": 59.0, "slide_d_end": 59.0,
+            "grow_d1_start": 600.0, "grow_d1_end": 7.0,
+            "grow_d2_start": 1500.0, "grow_d2_end": 3.0,
+            "grow_d3_start": 600.0, "grow_d3_end": 5.0,
+            "weight_slide_start": 0.05, "weight_slide_end": 0.05,
+            "weight_d1_start": 0.2, "weight_d1_end": 0.2,
+            "weight_d2_start": 0.7, "weight_d2_end": 0.7,
+            "weight_d3_start": 0.0, "weight_d3_end": 0.0
+        }"#;
+        let p: McParams = serde_json::from_str(flat).unwrap();
+        assert_eq!(
+            p.grow_d1,
+            Schedule::Linear {
+                start: 600.0,
+                end: 7.0
+            }
+        );
+
+        let mixed = flat.replace(
+            r#""grow_d1_start": 600.0, "grow_d1_end": 7.0,"#,
+            r#""grow_d1": {"type": "log", "start": 600.0, "end": 7.0},"#,
+        );
+        let p: McParams = serde_json::from_str(&mixed).unwrap();
+        assert_eq!(
+            p.grow_d1,
+            Schedule::Log {
+                start: 600.0,
+                end: 7.0
+            }
+        );
+
+        let p: McParams = serde_json::from_str(r#"{"grow_d1_end": 7.0}"#).unwrap();
+        assert_eq!(
+            p.grow_d1,
+            Schedule::Linear {
+                start: 661.4780032749206,
+                end: 7.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_merge_errors() {
+        let mut p = DEFAULT_PARAMS;
+        p.merge(
+            r#"{"grow_d1": {"type": "log", "start": 600.0, "end": 7.0}}"#,
+            "test",
+        )
+        .unwrap();
+        assert!(p.merge(r#"{"grow_d1_end": 5.0}"#, "test").is_err());
+        assert!(p
+            .merge(
+                r#"{"slide_d": {"type": "linear", "start": 1.0, "end": 1.0}, "slide_d_end": 2.0}"#,
+                "test"
+            )
+            .is_err());
+        assert!(p.merge(r#"{"tmp0": 1.0}"#, "test").is_err());
+        assert!(p.merge("@/no/such/file.json", "test").is_err());
+    }
+
+    #[test]
+    fn test_serialize_roundtrip() {
+        let mut p = DEFAULT_PARAMS;
+        p.grow_d2 = Schedule::Piecewise {
+            knots: vec![(0.0, 1000.0), (0.3, 50.0), (1.0, 3.0)].into(),
+        };
+        let json = serde_json::to_string(&p).unwrap();
+        let q: McParams = serde_json::from_str(&json).unwrap();
+        assert_eq!(json, serde_json::to_string(&q).unwrap());
+        assert_eq!(p.grow_d2, q.grow_d2);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(DEFAULT_PARAMS.validate().is_ok());
+        let mut p = DEFAULT_PARAMS;
+        p.temp1 = p.temp0 * 2.0;
+        assert!(p.validate().is_err());
+        let mut p = DEFAULT_PARAMS;
+        p.weight_d1 = Schedule::constant(-0.1);
+        assert!(p.validate().is_err());
+        let mut p = DEFAULT_PARAMS;
+        p.weight_slide = Schedule::constant(0.0);
+        p.weight_d1 = Schedule::constant(0.0);
+        p.weight_d2 = Schedule::Linear {
+            start: 1.0,
+            end: 0.0,
+        };
+        assert!(p.validate().is_err());
+        assert!(serde_json::from_str::<McParams>(r#"{"temp0": -1.0}"#).is_err());
+    }
+
+    #[test]
+    fn test_stagnation_json() {
+        let mut p = DEFAULT_PARAMS;
+        p.merge(
+            r#"{"stagnation": {"window": 100000, "action": {"type": "reheat", "fraction": 0.3}}}"#,
+            "test",
+        )
+        .unwrap();
+        assert_eq!(
+            p.stagnation,
+            Some(Stagnation {
+                window: 100000,
+                action: StagnationAction::Reheat { fraction: 0.3 },
+            })
+        );
+        let json = serde_json::to_string(&p).unwrap();
+        let q: McParams = serde_json::from_str(&json).unwrap();
+        assert_eq!(p.stagnation, q.stagnation);
+
+        p.merge(r#"{"stagnation": null}"#, "test").unwrap();
+        assert_eq!(p.stagnation, None);
+        assert!(!serde_json::to_string(&p).unwrap().contains("stagnation"));
+
+        assert!(serde_json::from_str::<McParams>(
+            r#"{"stagnation": {"window": 1000, "action": {"type": "perturb", "k": 0}}}"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "learn")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    params::{McParams, ParamsError},
+    Annealer, Budget, Input, Rect, SeedRng, Stats,
+};
+
+/// 予算を分けて複数回始める設定
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Portfolio {
+    /// 始める数
+    /// k 番目は seed + k で始める
+    pub starts: usize,
+    /// k 番目は params[k % params.len()] で始める
+    /// 空なら全部 RunConfig の params
+    #[cfg_attr(
+        feature = "learn",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub params: Vec<McParams>,
+    /// 逐次半減の回数
+    /// 予算を rounds + 1 段に等分し、段ごとに最良スコアで上位半分（切り上げ）だけ続ける
+    #[cfg_attr(feature = "learn", serde(default))]
+    pub rounds: usize,
+}
+
+impl Portfolio {
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        if self.starts == 0 {
+            return Err(ParamsError::Invalid(
+                "portfolio starts must be positive".to_owned(),
+            ));
+        }
+        for p in self.params.iter() {
+            p.validate()?;
+        }
+        Ok(())
+    }
+
+    /// 段ごとに続けるスタートの数
+    fn survivors(&self) -> Vec<usize> {
+        let mut active = self.starts;
+        let mut survivors = vec![active];
+        for _ in 0..self.rounds {
+            active = (active + 1) / 2;
+            survivors.push(active);
+        }
+        survivors
+    }
+}
+
+/// 段ごとの1スタートあたりの予算の累積
+/// 予算を段の数で等分し、段の中では残っているスタートで等分する（単位は budget と同じ）
+fn rung_ends(budget: Budget, survivors: &[usize]) -> Vec<Budget> {
+    let rungs = survivors.len();
+    match budget {
+        Budget::Time(limit) => {
+            let phase = limit / rungs as u32;
+            let mut end = Duration::from_secs(0);
+            survivors
+                .iter()
+                .map(|&active| {
+                    end += phase / active as u32;
+                    Budget::Time(end)
+                })
+                .collect()
+        }
+        Budget::Iterations(n) => {
+            let phase = n / rungs as u64;
+            let mut end = 0;
+            survivors
+                .iter()
+                .map(|&active| {
+                    end += phase / active as u64;
+                    Budget::Iterations(end)
+                })
+                .collect()
+        }
+    }
+}
+
+struct Start<R> {
+    index: usize,
+    rng: R,
+    annealer: Annealer,
+    params: McParams,
+    /// これまでに焼きなました時間（提案数は annealer.stats.all）
+    elapsed: Duration,
+}
+
+impl<R: SeedRng> Start<R> {
+    /// 使った予算が end になるまで、horizon を t = 1 とするスケジュールで焼きなます
+    /// end と horizon は同じ単位
+    fn advance(&mut self, input: &Input, end: Budget, horizon: Budget) {
+        let now = Instant::now();
+        loop {
+            let t = match (end, horizon) {
+                (Budget::Time(end), Budget::Time(horizon)) => {
+                    let used = self.elapsed + now.elapsed();
+                    if used >= end {
+                        break;
+                    }
+                    used.as_secs_f64() / horizon.as_secs_f64()
+                }
+                (Budget::Iterations(end), Budget::Iterations(horizon)) => {
+                    let used = self.annealer.stats.all;
+                    if used >= end {
+                        break;
+                    }
+                    used as f64 / horizon as f64
+                }
+                _ => unreachable!("end and horizon must have the same unit"),
+            };
+            self.annealer
+                .step(&mut self.rng, &self.params, input, t.min(1.0));
+        }
+        self.elapsed += now.elapsed();
+    }
+}
+
+/// 予算を分けて複数回始め、最良解を返す
+///
+/// 逐次半減では、最後まで残ったスタートが t = 1 に達するように各スタートのスケジュールを決める。
+/// 途中で落ちたスタートは t < 1 のところで止まる。
+/// Stats は全スタートの合計で、starts にスタートごとの (提案数, 最良スコア) が入る。
+pub fn portfolio<R: SeedRng>(
+    seed: u64,
+    params: &McParams,
+    input: &Input,
+    budget: Budget,
+    config: &Portfolio,
+) -> (f64, Vec<Rect>, Stats) {
+    let survivors = config.survivors();
+    let ends = rung_ends(budget, &survivors);
+    let horizon = *ends.last().unwrap();
+
+    let mut starts = (0..config.starts)
+        .map(|k| {
+            let params = if config.params.is_empty() {
+                params.clone()
+            } else {
+                config.params[k % config.params.len()].clone()
+            };
+            Start {
+                index: k,
+                rng: R::seeded(seed.wrapping_add(k as u64) as u128),
+                annealer: Annealer::new(&params, input),
+                params,
+                elapsed: Duration::from_secs(0),
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut results = Vec::with_capacity(config.starts);
+    let deadline = match budget {
+        Budget::Time(limit) => Some(Instant::now() + limit),
+        Budget::Iterations(_) => None,
+    };
+    for (j, &end) in ends.iter().enumerate() {
+        for start in starts.iter_mut() {
+            // 時間で打ち切るときは全体の締め切りも守る
+            let end = match (end, deadline) {
+                (Budget::Time(end), Some(deadline)) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    Budget::Time(end.min(start.elapsed + left))
+                }
+                _ => end,
+            };
+            start.advance(input, end, horizon);
+        }
+        starts.sort_by(|a, b| {
+            b.annealer
+                .state
+                .best_score
+                .partial_cmp(&a.annealer.state.best_score)
+                .unwrap()
+        });
+        let keep = survivors.get(j + 1).copied().unwrap_or(0);
+        while starts.len() > keep.max(1) {
+            results.push(starts.pop().unwrap());
+        }
+    }
+    results.extend(starts);
+    results.sort_by_key(|s| s.index);
+
+    let mut stats = Stats::default();
+    let mut best: Option<(f64, Vec<Rect>)> = None;
+    for start in results {
+        let (score, rects, s) = start.annealer.finish();
+        stats.all += s.all;
+        stats.tried += s.tried;
+        stats.valid += s.valid;
+        stats.ac += s.ac;
+        stats.starts.push((s.all, score));
+        if best.as_ref().map_or(true, |b| score > b.0) {
+            best = Some((score, rects));
+        }
+    }
+    let (score, rects) = best.unwrap();
+    (score, rects, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_pcg::Mcg128Xsl64;
+
+    use super::super::params::DEFAULT_PARAMS;
+    use super::*;
+
+    #[test]
+    fn test_portfolio() {
+        let points = vec![(100, 100), (5000, 5000), (9000, 1000), (5100, 5100)];
+        let input = Input {
+            rects: points
+                .iter()
+                .map(|&(x, y)| Rect::new(x, x + 1, y, y + 1))
+                .collect(),
+            points,
+            sizes: vec![1_000_000, 2_000_000, 500_000, 800_000],
+        };
+        let config = Portfolio {
+            starts: 4,
+            params: Vec::new(),
+            rounds: 2,
+        };
+        assert_eq!(config.survivors(), vec![4, 2, 1]);
+        let ends = rung_ends(
+            Budget::Time(Duration::from_millis(1200)),
+            &config.survivors(),
+        );
+        let ends = ends
+            .iter()
+            .map(|end| match end {
+                Budget::Time(end) => end.as_millis(),
+                Budget::Iterations(_) => panic!("unit changed"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ends, vec![100, 300, 700]);
+        let budget = Budget::Iterations(120_000);
+        let (score, best, stats) =
+            portfolio::<Mcg128Xsl64>(1, &DEFAULT_PARAMS, &input, budget, &config);
+        // 1段 40000 回を 4, 2, 1 スタートで分ける
+        let proposals = stats.starts.iter().map(|s| s.0).collect::<Vec<_>>();
+        assert_eq!(proposals.iter().sum::<u64>(), stats.all);
+        assert_eq!(proposals.iter().filter(|&&p| p == 10_000).count(), 2);
+        assert_eq!(proposals.iter().filter(|&&p| p == 30_000).count(), 1);
+        assert_eq!(proposals.iter().filter(|&&p| p == 70_000).count(), 1);
+        assert!(stats.starts.iter().all(|s| s.1 <= score));
+        for (i, rect) in best.iter().enumerate() {
+            assert!(rect.contain(input.points[i].0, input.points[i].1));
+            assert!(best[i + 1..].iter().all(|other| !rect.intersect(other)));
+        }
+    }
+}
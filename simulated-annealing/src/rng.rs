@@ -0,0 +1,159 @@
+use rand::{Error, RngCore, SeedableRng};
+use rand_pcg::Mcg128Xsl64;
+
+/// 焼きなましで使う乱数生成器
+///
+/// 各解法は seed から `seeded` で作る。Mcg128Xsl64 は `Mcg128Xsl64::new(seed)` なので、
+/// これまでと同じ seed で同じ結果になる。
+pub trait SeedRng: RngCore + SeedableRng {
+    fn seeded(seed: u128) -> Self {
+        // 上位 64 ビット（tempering のレプリカ番号など）も混ぜる
+        let high = ((seed >> 64) as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        Self::seed_from_u64(seed as u64 ^ high)
+    }
+}
+
+impl SeedRng for Mcg128Xsl64 {
+    fn seeded(seed: u128) -> Self {
+        Mcg128Xsl64::new(seed)
+    }
+}
+
+/// feature で選ぶ既定の乱数生成器（rng-wyrand, rng-xorshift の順に優先、なければ Mcg128Xsl64）
+#[cfg(feature = "rng-wyrand")]
+pub type DefaultRng = WyRand;
+#[cfg(all(feature = "rng-xorshift", not(feature = "rng-wyrand")))]
+pub type DefaultRng = Xorshift64Star;
+#[cfg(not(any(feature = "rng-xorshift", feature = "rng-wyrand")))]
+pub type DefaultRng = Mcg128Xsl64;
+
+fn fill_bytes_via_u64<R: RngCore>(rng: &mut R, dest: &mut [u8]) {
+    for chunk in dest.chunks_mut(8) {
+        let bytes = rng.next_u64().to_le_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+}
+
+/// xorshift64*（Vigna, 2016）
+#[derive(Debug, Clone)]
+pub struct Xorshift64Star {
+    state: u64,
+}
+
+impl RngCore for Xorshift64Star {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_bytes_via_u64(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Xorshift64Star {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: [u8; 8]) -> Xorshift64Star {
+        // 状態が 0 だとずっと 0 になる
+        let state = u64::from_le_bytes(seed);
+        Xorshift64Star {
+            state: if state == 0 {
+                0x9e37_79b9_7f4a_7c15
+            } else {
+                state
+            },
+        }
+    }
+}
+
+impl SeedRng for Xorshift64Star {}
+
+/// wyrand（wyhash の乱数生成器）
+#[derive(Debug, Clone)]
+pub struct WyRand {
+    state: u64,
+}
+
+impl RngCore for WyRand {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0xa076_1d64_78bd_642f);
+        let t = self.state as u128 * (self.state ^ 0xe703_7ed1_a0b4_28db) as u128;
+        (t >> 64) as u64 ^ t as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill_bytes_via_u64(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for WyRand {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: [u8; 8]) -> WyRand {
+        WyRand {
+            state: u64::from_le_bytes(seed),
+        }
+    }
+}
+
+impl SeedRng for WyRand {}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    /// [0, 1) の平均と、2つに分けたときの偏りをざっと見る
+    fn check<R: SeedRng>() {
+        let mut rng = R::seeded(1);
+        let n = 100_000;
+        let mut sum = 0.0;
+        let mut low = 0;
+        for _ in 0..n {
+            let v = rng.gen::<f64>();
+            assert!((0.0..1.0).contains(&v));
+            sum += v;
+            low += (v < 0.5) as i32;
+        }
+        assert!((sum / n as f64 - 0.5).abs() < 0.01);
+        assert!((low - n / 2).abs() < n / 100);
+        // seed が違えば違う列になる
+        assert_ne!(R::seeded(1).next_u64(), R::seeded(2).next_u64());
+        assert_ne!(R::seeded(1).next_u64(), R::seeded(1 << 64).next_u64());
+    }
+
+    #[test]
+    fn test_rngs() {
+        check::<Mcg128Xsl64>();
+        check::<Xorshift64Star>();
+        check::<WyRand>();
+    }
+}
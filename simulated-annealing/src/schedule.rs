@@ -0,0 +1,245 @@
+use std::borrow::Cow;
+
+#[cfg(feature = "learn")]
+use serde::{Deserialize, Serialize};
+
+/// t ∈ [0, 1] に対して値を返すスケジュール
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum Schedule {
+    /// start * (1 - t) + end * t
+    Linear { start: f64, end: f64 },
+    /// start^(1 - t) * end^t
+    /// start, end は正であること
+    Log { start: f64, end: f64 },
+    /// start + (end - start) * t^exponent
+    Power { start: f64, end: f64, exponent: f64 },
+    /// (t, value) の折れ線
+    /// 範囲外は端の値
+    Piecewise { knots: Cow<'static, [(f64, f64)]> },
+}
+
+impl Schedule {
+    pub const fn constant(value: f64) -> Schedule {
+        Schedule::Linear {
+            start: value,
+            end: value,
+        }
+    }
+
+    pub fn at(&self, t: f64) -> f64 {
+        match self {
+            Schedule::Linear { start, end } => start * (1.0 - t) + end * t,
+            Schedule::Log { start, end } => start.powf(1.0 - t) * end.powf(t),
+            Schedule::Power {
+                start,
+                end,
+                exponent,
+            } => start + (end - start) * t.powf(*exponent),
+            Schedule::Piecewise { knots } => piecewise(knots, t),
+        }
+    }
+
+    /// t ∈ [0, 1] でとりうる値の (最小, 最大)
+    pub fn bounds(&self) -> (f64, f64) {
+        match self {
+            Schedule::Linear { start, end }
+            | Schedule::Log { start, end }
+            | Schedule::Power { start, end, .. } => (start.min(*end), start.max(*end)),
+            Schedule::Piecewise { knots } => knots.iter().fold(
+                (std::f64::INFINITY, std::f64::NEG_INFINITY),
+                |(lo, hi), &(_, v)| (lo.min(v), hi.max(v)),
+            ),
+        }
+    }
+
+    /// スケジュールとして意味をなすかチェック
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Schedule::Linear { start, end } => check_finite(&[*start, *end]),
+            Schedule::Log { start, end } => {
+                check_finite(&[*start, *end])?;
+                if *start <= 0.0 || *end <= 0.0 {
+                    return Err("log schedule requires positive start and end".to_owned());
+                }
+                Ok(())
+            }
+            Schedule::Power {
+                start,
+                end,
+                exponent,
+            } => {
+                check_finite(&[*start, *end, *exponent])?;
+                if *exponent <= 0.0 {
+                    return Err("power schedule requires positive exponent".to_owned());
+                }
+                Ok(())
+            }
+            Schedule::Piecewise { knots } => {
+                if knots.is_empty() {
+                    return Err("piecewise schedule requires at least one knot".to_owned());
+                }
+                for &(t, v) in knots.iter() {
+                    check_finite(&[t, v])?;
+                }
+                if knots.windows(2).any(|w| w[0].0 > w[1].0) {
+                    return Err("piecewise knots must be sorted by t".to_owned());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// self と other を (1 - s) : s で混ぜる
+    /// 同じ種類（折れ線は同じ t の節点）なら値ごとに線形補間し、そうでなければ近い方を返す
+    pub fn lerp(&self, other: &Schedule, s: f64) -> Schedule {
+        let mix = |a: f64, b: f64| a * (1.0 - s) + b * s;
+        match (self, other) {
+            (Schedule::Linear { start: s0, end: e0 }, Schedule::Linear { start: s1, end: e1 }) => {
+                Schedule::Linear {
+                    start: mix(*s0, *s1),
+                    end: mix(*e0, *e1),
+                }
+            }
+            (Schedule::Log { start: s0, end: e0 }, Schedule::Log { start: s1, end: e1 }) => {
+                Schedule::Log {
+                    start: mix(*s0, *s1),
+                    end: mix(*e0, *e1),
+                }
+            }
+            (
+                Schedule::Power {
+                    start: s0,
+                    end: e0,
+                    exponent: x0,
+                },
+                Schedule::Power {
+                    start: s1,
+                    end: e1,
+                    exponent: x1,
+                },
+            ) => Schedule::Power {
+                start: mix(*s0, *s1),
+                end: mix(*e0, *e1),
+                exponent: mix(*x0, *x1),
+            },
+            (Schedule::Piecewise { knots: k0 }, Schedule::Piecewise { knots: k1 })
+                if k0.len() == k1.len() && k0.iter().zip(k1.iter()).all(|(a, b)| a.0 == b.0) =>
+            {
+                Schedule::Piecewise {
+                    knots: k0
+                        .iter()
+                        .zip(k1.iter())
+                        .map(|(a, b)| (a.0, mix(a.1, b.1)))
+                        .collect::<Vec<_>>()
+                        .into(),
+                }
+            }
+            _ => {
+                if s < 0.5 {
+                    self.clone()
+                } else {
+                    other.clone()
+                }
+            }
+        }
+    }
+
+    /// const に埋め込める Rust の式
+    #[cfg(feature = "learn")]
+    pub fn to_rust(&self) -> String {
+        match self {
+            Schedule::Linear { start, end } => format!(
+                "super::schedule::Schedule::Linear {{ start: {:?}, end: {:?} }}",
+                start, end
+            ),
+            Schedule::Log { start, end } => format!(
+                "super::schedule::Schedule::Log {{ start: {:?}, end: {:?} }}",
+                start, end
+            ),
+            Schedule::Power {
+                start,
+                end,
+                exponent,
+            } => format!(
+                "super::schedule::Schedule::Power {{ start: {:?}, end: {:?}, exponent: {:?} }}",
+                start, end, exponent
+            ),
+            Schedule::Piecewise { knots } => format!(
+                "super::schedule::Schedule::Piecewise {{ knots: std::borrow::Cow::Borrowed(&{:?}) }}",
+                knots
+            ),
+        }
+    }
+}
+
+fn check_finite(values: &[f64]) -> Result<(), String> {
+    if values.iter().all(|v| v.is_finite()) {
+        Ok(())
+    } else {
+        Err("schedule values must be finite".to_owned())
+    }
+}
+
+fn piecewise(knots: &[(f64, f64)], t: f64) -> f64 {
+    let (first, last) = match (knots.first(), knots.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return 0.0,
+    };
+    if t <= first.0 {
+        return first.1;
+    }
+    if t >= last.0 {
+        return last.1;
+    }
+    for w in knots.windows(2) {
+        let (t0, v0) = w[0];
+        let (t1, v1) = w[1];
+        if t <= t1 {
+            if t1 <= t0 {
+                return v1;
+            }
+            let s = (t - t0) / (t1 - t0);
+            return v0 * (1.0 - s) + v1 * s;
+        }
+    }
+    last.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear_and_log() {
+        let s = Schedule::Linear {
+            start: 10.0,
+            end: 2.0,
+        };
+        assert_eq!(s.at(0.0), 10.0);
+        assert_eq!(s.at(0.5), 6.0);
+        assert_eq!(s.at(1.0), 2.0);
+
+        let s = Schedule::Log {
+            start: 100.0,
+            end: 1.0,
+        };
+        assert!((s.at(0.5) - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_piecewise() {
+        let s = Schedule::Piecewise {
+            knots: vec![(0.2, 1.0), (0.6, 5.0), (1.0, 3.0)].into(),
+        };
+        assert_eq!(s.at(0.0), 1.0);
+        assert_eq!(s.at(0.2), 1.0);
+        assert!((s.at(0.4) - 3.0).abs() < 1e-9);
+        assert!((s.at(0.8) - 4.0).abs() < 1e-9);
+        assert_eq!(s.at(1.0), 3.0);
+    }
+}
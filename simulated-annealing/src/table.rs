@@ -0,0 +1,236 @@
+use std::borrow::Cow;
+
+#[cfg(feature = "learn")]
+use serde::{Deserialize, Serialize};
+
+use super::params::{McParams, ParamsError};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(deny_unknown_fields)
+)]
+pub struct ParamsEntry {
+    pub n: usize,
+    pub params: McParams,
+}
+
+/// 入力サイズ n ごとのパラメータ
+///
+/// entries は n の昇順
+/// * interpolate = false: n 以下で最大の entry を使う（区間の下端として扱う）
+/// * interpolate = true: 前後の entry を n で線形補間する
+///
+/// 範囲外はどちらも端の entry
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(deny_unknown_fields)
+)]
+pub struct ParamsTable {
+    #[cfg_attr(feature = "learn", serde(default))]
+    pub interpolate: bool,
+    pub entries: Cow<'static, [ParamsEntry]>,
+}
+
+/// 提出時に使われるテーブル
+/// `embed` で JSON から生成したもので、PARAMS_ENTRIES と一緒に差し替える
+pub const PARAMS_TABLE: super::table::ParamsTable = super::table::ParamsTable {
+    interpolate: false,
+    entries: std::borrow::Cow::Borrowed(PARAMS_ENTRIES),
+};
+const PARAMS_ENTRIES: &[super::table::ParamsEntry] = &[super::table::ParamsEntry {
+    n: 0,
+    params: super::params::DEFAULT_PARAMS,
+}];
+
+/// テーブルファイルを指定する環境変数
+#[cfg(feature = "learn")]
+pub const PARAMS_TABLE_ENV: &str = "AHC001_PARAMS_TABLE";
+
+impl ParamsTable {
+    pub fn select(&self, n: usize) -> McParams {
+        let entries = &self.entries;
+        let i = entries.iter().take_while(|e| e.n <= n).count();
+        if i == 0 {
+            return entries[0].params.clone();
+        }
+        let lower = &entries[i - 1];
+        match entries.get(i) {
+            Some(upper) if self.interpolate && lower.n < n => {
+                let s = (n - lower.n) as f64 / (upper.n - lower.n) as f64;
+                lower.params.lerp(&upper.params, s)
+            }
+            _ => lower.params.clone(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        if self.entries.is_empty() {
+            return Err(ParamsError::Invalid(
+                "params table must have at least one entry".to_owned(),
+            ));
+        }
+        if self.entries.windows(2).any(|w| w[0].n >= w[1].n) {
+            return Err(ParamsError::Invalid(
+                "params table entries must be strictly sorted by n".to_owned(),
+            ));
+        }
+        for e in self.entries.iter() {
+            e.params
+                .validate()
+                .map_err(|err| ParamsError::Invalid(format!("entry n={}: {}", e.n, err)))?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "learn")]
+    pub fn load(path: &str) -> Result<ParamsTable, ParamsError> {
+        let json = std::fs::read_to_string(path).map_err(|e| ParamsError::Io {
+            path: path.to_owned(),
+            source: e,
+        })?;
+        let table: ParamsTable = serde_json::from_str(&json).map_err(|e| ParamsError::Json {
+            origin: path.to_owned(),
+            source: e,
+        })?;
+        table.validate()?;
+        Ok(table)
+    }
+
+    /// `PARAMS_TABLE` と `PARAMS_ENTRIES` を置き換えられる Rust のコード
+    /// パスは table.rs から `super::` で書くので、use がなくても貼り付けられる
+    #[cfg(feature = "learn")]
+    pub fn to_rust(&self) -> String {
+        let mut s = format!(
+            "pub const PARAMS_TABLE: super::table::ParamsTable = super::table::ParamsTable {{\n    interpolate: {},\n    entries: std::borrow::Cow::Borrowed(PARAMS_ENTRIES),\n}};\n",
+            self.interpolate
+        );
+        s += "const PARAMS_ENTRIES: &[super::table::ParamsEntry] = &[\n";
+        for e in self.entries.iter() {
+            let params = e.params.to_rust().replace('\n', "\n        ");
+            s += &format!(
+                "    super::table::ParamsEntry {{\n        n: {},\n        params: {},\n    }},\n",
+                e.n, params
+            );
+        }
+        s += "];\n";
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{params::DEFAULT_PARAMS, schedule::Schedule};
+    use super::*;
+    // generated の中の `super::table::ParamsTable` などはここから解決される
+    #[cfg(feature = "learn")]
+    use super::super::{acceptance, compact, params, schedule, table};
+
+    fn params_with_temp0(temp0: f64) -> McParams {
+        let mut p = DEFAULT_PARAMS;
+        p.temp0 = temp0;
+        p.slide_d = Schedule::constant(temp0);
+        p
+    }
+
+    #[test]
+    fn test_select() {
+        let mut table = ParamsTable {
+            interpolate: false,
+            entries: vec![
+                ParamsEntry {
+                    n: 50,
+                    params: params_with_temp0(1.0),
+                },
+                ParamsEntry {
+                    n: 150,
+                    params: params_with_temp0(100.0),
+                },
+            ]
+            .into(),
+        };
+        assert!(table.validate().is_ok());
+        assert_eq!(table.select(10).temp0, 1.0);
+        assert_eq!(table.select(100).temp0, 1.0);
+        assert_eq!(table.select(150).temp0, 100.0);
+        assert_eq!(table.select(200).temp0, 100.0);
+
+        table.interpolate = true;
+        assert_eq!(table.select(50).temp0, 1.0);
+        assert!((table.select(100).temp0 - 10.0).abs() < 1e-9);
+        assert_eq!(table.select(100).slide_d, Schedule::constant(50.5));
+        assert_eq!(table.select(200).temp0, 100.0);
+    }
+
+    // to_rust の出力を table.rs に貼ったときと同じ形で読み込む
+    #[cfg(feature = "learn")]
+    mod generated {
+        include!("../testdata/params_table.rs");
+    }
+
+    #[cfg(feature = "learn")]
+    fn varied_table() -> ParamsTable {
+        use acceptance::Acceptance;
+        use compact::Toward;
+        use params::{Lns, Stagnation, StagnationAction};
+
+        let mut p0 = DEFAULT_PARAMS;
+        p0.temp0 = 12.5;
+        p0.slide_d = Schedule::Log {
+            start: 30.0,
+            end: 1.5,
+        };
+        p0.grow_d1 = Schedule::Piecewise {
+            knots: vec![(0.0, 8.0), (0.25, 4.0), (1.0, 1.0)].into(),
+        };
+        p0.stagnation = Some(Stagnation {
+            window: 5000,
+            action: StagnationAction::Compact {
+                toward: Toward::High,
+            },
+        });
+        p0.lns = Some(Lns {
+            interval: 4000,
+            size: 1200,
+            repair: 300,
+        });
+        p0.acceptance = Acceptance::GreatDeluge { rain: 1e-7 };
+        let mut p1 = DEFAULT_PARAMS;
+        p1.grow_d2 = Schedule::Power {
+            start: 20.0,
+            end: 2.0,
+            exponent: 1.5,
+        };
+        p1.stagnation = Some(Stagnation {
+            window: 10000,
+            action: StagnationAction::Reheat { fraction: 0.5 },
+        });
+        ParamsTable {
+            interpolate: true,
+            entries: vec![
+                ParamsEntry { n: 50, params: p0 },
+                ParamsEntry { n: 150, params: p1 },
+            ]
+            .into(),
+        }
+    }
+
+    #[cfg(feature = "learn")]
+    #[test]
+    fn test_to_rust_round_trip() {
+        let table = varied_table();
+        assert!(table.validate().is_ok());
+        assert_eq!(table.to_rust(), include_str!("../testdata/params_table.rs"));
+        let parsed = generated::PARAMS_TABLE;
+        assert_eq!(format!("{:?}", parsed), format!("{:?}", table));
+        assert_eq!(parsed.to_rust(), table.to_rust());
+    }
+
+    #[test]
+    fn test_default_table() {
+        assert!(PARAMS_TABLE.validate().is_ok());
+    }
+}
@@ -0,0 +1,300 @@
+use std::time::Instant;
+
+#[cfg(feature = "learn")]
+use serde::{Deserialize, Serialize};
+
+use super::{params::ParamsError, progress, Budget, Input, QTree, Rect, State, Stats, L};
+
+/// タブー探索の設定
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Tabu {
+    /// 近傍を調べる長方形の数（動かせるものをスコアの低い順に）
+    pub candidates: usize,
+    /// 動かした (長方形, 辺) を禁止する反復回数
+    pub tenure: u64,
+    /// 辺を動かす幅（それぞれ両方向）
+    pub steps: Vec<i16>,
+}
+
+impl Tabu {
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        if self.candidates == 0 {
+            return Err(ParamsError::Invalid(
+                "tabu candidates must be positive".to_owned(),
+            ));
+        }
+        if self.steps.is_empty() || self.steps.iter().any(|&d| !(0 < d && d <= L)) {
+            return Err(ParamsError::Invalid(format!(
+                "tabu steps must be non-empty and in [1, {}]",
+                L
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Tabu {
+    fn default() -> Tabu {
+        Tabu {
+            candidates: 8,
+            tenure: 500,
+            steps: vec![1, 4, 16, 64, 256, 1024],
+        }
+    }
+}
+
+/// 同じ配置に戻ったときの浮動小数点の誤差で aspiration が効かないようにする
+const EPS: f64 = 1e-9;
+
+fn move_side(rect: &Rect, side: usize, d: i16) -> Option<Rect> {
+    match side {
+        0 => rect.grow_x1(d),
+        1 => rect.grow_x2(d),
+        2 => rect.grow_y1(d),
+        _ => rect.grow_y2(d),
+    }
+}
+
+/// 近傍の1つの手
+struct Move {
+    /// スコアの差
+    diff: f64,
+    i: usize,
+    side: usize,
+    d: i16,
+    new: Rect,
+    /// 押し戻した長方形
+    pushed: Vec<(usize, Rect)>,
+}
+
+/// rects[i] の side の辺を動かして new にするとき、広がった部分 grow に重なる長方形の
+/// 向かい合う辺を new の辺まで押し戻したもの
+/// 点を含まなくなる長方形があれば None
+fn push(
+    qtree: &QTree,
+    rects: &[Rect],
+    points: &[(i16, i16)],
+    i: usize,
+    side: usize,
+    new: &Rect,
+    grow: &Rect,
+) -> Option<Vec<(usize, Rect)>> {
+    let mut hits = Vec::new();
+    qtree.for_each_intersect(grow, rects, |j| {
+        if j != i {
+            hits.push(j);
+        }
+    });
+    // 四分木の順ではなく番号順に並べて、スコアの差の足し方を決まった順にする
+    hits.sort_unstable();
+    let mut pushed = Vec::with_capacity(hits.len());
+    for j in hits {
+        let other = &rects[j];
+        let cut = match side {
+            0 => Rect::new(other.x1, new.x1, other.y1, other.y2),
+            1 => Rect::new(new.x2, other.x2, other.y1, other.y2),
+            2 => Rect::new(other.x1, other.x2, other.y1, new.y1),
+            _ => Rect::new(other.x1, other.x2, new.y2, other.y2),
+        };
+        if !cut.contain(points[j].0, points[j].1) {
+            return None;
+        }
+        pushed.push((j, cut));
+    }
+    Some(pushed)
+}
+
+/// 辺を動かす近傍でのタブー探索
+///
+/// 毎回、スコアの低い順に禁止されていない手がある長方形を candidates 個選び、その4辺を steps の幅で
+/// 動かす近傍から最もスコアが上がる（悪化が小さい）ものを選ぶ。広げた辺が他の長方形に当たるときは、
+/// 相手の向かい合う辺を押し戻す手にする。動かした (長方形, 辺) は tenure 回禁止するが、
+/// 最良スコアを更新するなら禁止されていても選ぶ（aspiration）。乱数は使わない。
+/// Stats の all は調べた近傍の数、ac は動かした回数。
+pub fn tabu(input: &Input, budget: Budget, config: &Tabu) -> (f64, Vec<Rect>, Stats) {
+    let now = Instant::now();
+    let n = input.rects.len();
+    let mut stats = Stats::default();
+    let mut state = State::new(input.rects.to_vec(), &input.sizes);
+    let mut tabu_until = vec![[0; 4]; n];
+    let mut order = (0..n).collect::<Vec<_>>();
+    // 適用できなかった (長方形, 辺, 幅)。配置が変わるまで選ばない
+    let mut rejected: Vec<(usize, usize, i16)> = Vec::new();
+    let mut iteration = 0;
+    while progress(budget, now, stats.all).is_some() {
+        iteration += 1;
+        order.sort_by(|&a, &b| state.scores[a].partial_cmp(&state.scores[b]).unwrap());
+        let mut chosen: Option<Move> = None;
+        let mut movable = 0;
+        for &i in order.iter() {
+            if movable >= config.candidates {
+                break;
+            }
+            let rect = &state.rects[i];
+            let (x, y) = input.points[i];
+            let mut any = false;
+            for side in 0..4 {
+                for &d in config.steps.iter() {
+                    for &d in [d, -d].iter() {
+                        stats.all += 1;
+                        if rejected.contains(&(i, side, d)) {
+                            continue;
+                        }
+                        let new = match move_side(rect, side, d) {
+                            Some(new) => new,
+                            None => continue,
+                        };
+                        stats.tried += 1;
+                        if !new.contain(x, y) {
+                            continue;
+                        }
+                        let pushed = match rect.grow_rect(&new) {
+                            Some(grow) if state.qtree.intersect(&grow, &state.rects) => {
+                                match push(
+                                    &state.qtree,
+                                    &state.rects,
+                                    &input.points,
+                                    i,
+                                    side,
+                                    &new,
+                                    &grow,
+                                ) {
+                                    Some(pushed) => pushed,
+                                    None => continue,
+                                }
+                            }
+                            _ => Vec::new(),
+                        };
+                        stats.valid += 1;
+                        let diff = new.score(input.sizes[i]) - state.scores[i]
+                            + pushed
+                                .iter()
+                                .map(|(j, cut)| cut.score(input.sizes[*j]) - state.scores[*j])
+                                .sum::<f64>();
+                        let forbidden = tabu_until[i][side] > iteration
+                            || pushed
+                                .iter()
+                                .any(|&(j, _)| tabu_until[j][side ^ 1] > iteration);
+                        let aspiration = state.score + diff > state.best_score + EPS;
+                        if forbidden && !aspiration {
+                            continue;
+                        }
+                        any = true;
+                        if chosen.as_ref().map_or(true, |c| diff > c.diff) {
+                            chosen = Some(Move {
+                                diff,
+                                i,
+                                side,
+                                d,
+                                new,
+                                pushed,
+                            });
+                        }
+                    }
+                }
+            }
+            if any {
+                movable += 1;
+            }
+        }
+
+        if let Some(Move {
+            i,
+            side,
+            d,
+            new,
+            pushed,
+            ..
+        }) = chosen
+        {
+            // 先に押し戻して空けてから広げる。どれかが通らなければ戻して動かさない
+            state.begin();
+            let ok = pushed
+                .iter()
+                .all(|(j, cut)| state.apply(*j, cut.clone(), input))
+                && state.apply(i, new, input);
+            if !ok {
+                // 配置は変わらないので、除かなければ同じ手を選び続ける
+                state.rollback(&input.sizes);
+                rejected.push((i, side, d));
+                continue;
+            }
+            state.commit();
+            rejected.clear();
+            stats.ac += 1;
+            for (j, _) in pushed {
+                tabu_until[j][side ^ 1] = iteration + config.tenure;
+            }
+            tabu_until[i][side] = iteration + config.tenure;
+            if state.score > state.best_score + EPS {
+                state.update_best();
+            }
+        }
+    }
+    (state.best_score / n as f64, state.into_best(), stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use proconio::source::once::OnceSource;
+
+    use super::super::{guillotine, parse_source};
+    use super::*;
+
+    #[test]
+    fn test_push() {
+        let input = parse_source(OnceSource::new(
+            include_str!("../../tools/in/0000.txt").as_bytes(),
+        ));
+        let state = State::new(guillotine(&input.points, &input.sizes), &input.sizes);
+        let rects = &state.rects;
+        let mut pushes = 0;
+        for i in 0..rects.len() {
+            for side in 0..4 {
+                for &d in [16, 256].iter() {
+                    let new = match move_side(&rects[i], side, d) {
+                        Some(new) => new,
+                        None => continue,
+                    };
+                    let grow = match rects[i].grow_rect(&new) {
+                        Some(grow) => grow,
+                        None => continue,
+                    };
+                    let got = push(&state.qtree, rects, &input.points, i, side, &new, &grow);
+                    // 全部の長方形を見たときと同じものを押し戻す
+                    let hits = (0..rects.len())
+                        .filter(|&j| j != i && rects[j].intersect(&grow))
+                        .collect::<Vec<_>>();
+                    if let Some(pushed) = got {
+                        assert_eq!(pushed.iter().map(|p| p.0).collect::<Vec<_>>(), hits);
+                        pushes += pushed.len();
+                    }
+                }
+            }
+        }
+        assert!(pushes > 0);
+    }
+
+    #[test]
+    fn test_tabu() {
+        let input = parse_source(OnceSource::new(
+            include_str!("../../tools/in/0000.txt").as_bytes(),
+        ));
+        let config = Tabu::default();
+        assert!(config.validate().is_ok());
+        let budget = Budget::Iterations(200_000);
+        let (score, best, stats) = tabu(&input, budget, &config);
+        assert!(score > 0.1 && stats.ac > 0);
+        for (i, rect) in best.iter().enumerate() {
+            assert!(rect.contain(input.points[i].0, input.points[i].1));
+            assert!(best[i + 1..].iter().all(|other| !rect.intersect(other)));
+        }
+        // 乱数を使わないので同じ結果になる
+        assert_eq!(score, tabu(&input, budget, &config).0);
+    }
+}
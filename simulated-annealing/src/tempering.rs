@@ -0,0 +1,250 @@
+use std::{sync::Arc, time::Instant};
+
+use rand::Rng;
+#[cfg(feature = "learn")]
+use serde::{Deserialize, Serialize};
+
+use super::{
+    acceptance::Criteria,
+    params::{McParams, ParamsError},
+    progress, sweep_criteria, temperature, Budget, Input, Moves, Rect, SeedRng, State, Stats,
+};
+
+/// レプリカ交換法の設定
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "learn",
+    derive(Deserialize, Serialize),
+    serde(deny_unknown_fields)
+)]
+pub struct Tempering {
+    /// 各レプリカの温度の倍率（mc の温度スケジュールにかける）
+    /// 昇順で、先頭を 1.0 にすれば単独の mc と同じ温度のレプリカが入る
+    pub ladder: Vec<f64>,
+    /// 交換を試みる間隔（レプリカごとの提案数、2000 単位に切り上げ）
+    pub interval: u64,
+}
+
+impl Tempering {
+    /// 倍率が 1, ratio, ratio^2, ... の replicas 個のレプリカ
+    pub fn geometric(replicas: usize, ratio: f64, interval: u64) -> Tempering {
+        Tempering {
+            ladder: (0..replicas).map(|k| ratio.powi(k as i32)).collect(),
+            interval,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        if self.ladder.is_empty() {
+            return Err(ParamsError::Invalid(
+                "tempering ladder must not be empty".to_owned(),
+            ));
+        }
+        if !self.ladder.iter().all(|&s| s.is_finite() && s > 0.0) {
+            return Err(ParamsError::Invalid(
+                "tempering ladder must be positive".to_owned(),
+            ));
+        }
+        if self.ladder.windows(2).any(|w| w[0] > w[1]) {
+            return Err(ParamsError::Invalid(
+                "tempering ladder must be sorted".to_owned(),
+            ));
+        }
+        if self.interval == 0 {
+            return Err(ParamsError::Invalid(
+                "tempering interval must be positive".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+struct Replica<R> {
+    rng: R,
+    state: State,
+    /// params.acceptance の判定で、交換のときは state と一緒に入れ替える
+    criterion: Criteria,
+    stats: Stats,
+    scale: f64,
+}
+
+impl<R: SeedRng> Replica<R> {
+    /// interval 回か予算を使い切るまで焼きなます
+    fn advance(
+        &mut self,
+        params: &McParams,
+        input: &Input,
+        budget: Budget,
+        now: Instant,
+        interval: u64,
+    ) {
+        let end = self.stats.all + interval;
+        while self.stats.all < end {
+            let t = match progress(budget, now, self.stats.all) {
+                Some(t) => t,
+                None => break,
+            };
+            let moves = Moves::new(params, t, &self.state.bounds);
+            sweep_criteria(
+                &mut self.criterion,
+                &mut self.rng,
+                &mut self.state,
+                &moves,
+                input,
+                temperature(params, t) * self.scale,
+                &mut self.stats,
+            );
+        }
+    }
+}
+
+/// レプリカ交換法
+///
+/// ladder の倍率ごとにレプリカを作り、別々のスレッドで interval 回ずつ焼きなます。
+/// そのたびに隣り合う温度のレプリカの組（偶数番目と奇数番目から交互）で、
+/// Metropolis 基準で状態を交換する。
+/// budget はレプリカごとの量で、返すのは全レプリカを通じた最良解。
+/// Stats は全レプリカの合計で、exchanges に組ごとの交換の回数が入る。
+pub fn tempering<R: SeedRng + Send + 'static>(
+    seed: u64,
+    params: &McParams,
+    input: &Input,
+    budget: Budget,
+    config: &Tempering,
+) -> (f64, Vec<Rect>, Stats) {
+    let now = Instant::now();
+    let mut replicas = config
+        .ladder
+        .iter()
+        .enumerate()
+        .map(|(k, &scale)| Replica {
+            rng: R::seeded(seed as u128 ^ ((k as u128) << 64)),
+            state: State::new(input.rects.to_vec(), &input.sizes),
+            criterion: params.acceptance.criterion(input.rects.len()),
+            stats: Stats::default(),
+            scale,
+        })
+        .collect::<Vec<_>>();
+    let mut rng = R::seeded(seed as u128 ^ ((replicas.len() as u128) << 64));
+    let mut exchanges = vec![(0, 0); replicas.len().saturating_sub(1)];
+    let interval = (config.interval + 1999) / 2000 * 2000;
+    // スレッドに渡すので所有して共有する
+    let shared = Arc::new((params.clone(), input.clone()));
+    let mut round = 0;
+    while let Some(t) = progress(budget, now, replicas[0].stats.all) {
+        if replicas.len() == 1 {
+            replicas[0].advance(params, input, budget, now, interval);
+        } else {
+            let handles = replicas
+                .drain(..)
+                .map(|mut replica| {
+                    let shared = Arc::clone(&shared);
+                    std::thread::spawn(move || {
+                        replica.advance(&shared.0, &shared.1, budget, now, interval);
+                        replica
+                    })
+                })
+                .collect::<Vec<_>>();
+            replicas.extend(handles.into_iter().map(|h| h.join().unwrap()));
+        }
+
+        let temp = temperature(params, t);
+        for i in (round % 2..replicas.len().saturating_sub(1)).step_by(2) {
+            let beta_i = 1.0 / (temp * replicas[i].scale);
+            let beta_j = 1.0 / (temp * replicas[i + 1].scale);
+            let diff = replicas[i + 1].state.score - replicas[i].state.score;
+            exchanges[i].0 += 1;
+            if rng.gen::<f64>() < ((beta_i - beta_j) * diff).exp() {
+                exchanges[i].1 += 1;
+                let (lower, upper) = replicas.split_at_mut(i + 1);
+                std::mem::swap(&mut lower[i].state, &mut upper[0].state);
+                std::mem::swap(&mut lower[i].criterion, &mut upper[0].criterion);
+            }
+        }
+        round += 1;
+    }
+
+    let mut stats = Stats {
+        exchanges,
+        ..Stats::default()
+    };
+    for r in replicas.iter() {
+        stats.all += r.stats.all;
+        stats.tried += r.stats.tried;
+        stats.valid += r.stats.valid;
+        stats.ac += r.stats.ac;
+    }
+    let best = replicas
+        .into_iter()
+        .map(|r| r.state)
+        .max_by(|a, b| a.best_score.partial_cmp(&b.best_score).unwrap())
+        .unwrap();
+    let n = best.scores.len() as f64;
+    (best.best_score / n, best.into_best(), stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_pcg::Mcg128Xsl64;
+
+    use super::super::{params::DEFAULT_PARAMS, Acceptance};
+    use super::*;
+
+    #[test]
+    fn test_tempering() {
+        let points = vec![(100, 100), (5000, 5000), (9000, 1000), (5100, 5100)];
+        let input = Input {
+            rects: points
+                .iter()
+                .map(|&(x, y)| Rect::new(x, x + 1, y, y + 1))
+                .collect(),
+            points,
+            sizes: vec![1_000_000, 2_000_000, 500_000, 800_000],
+        };
+        let config = Tempering::geometric(3, 2.0, 10_000);
+        assert!(config.validate().is_ok());
+        let budget = Budget::Iterations(100_000);
+        let (score, best, stats) =
+            tempering::<Mcg128Xsl64>(1, &DEFAULT_PARAMS, &input, budget, &config);
+        assert_eq!(stats.all, 300_000);
+        assert_eq!(stats.exchanges.len(), 2);
+        assert!(stats
+            .exchanges
+            .iter()
+            .all(|&(tried, ac)| tried > 0 && ac <= tried));
+        for (i, rect) in best.iter().enumerate() {
+            assert!(rect.contain(input.points[i].0, input.points[i].1));
+            assert!(best[i + 1..].iter().all(|other| !rect.intersect(other)));
+        }
+        // 提案回数で打ち切れば再現する
+        let (again, _, _) = tempering::<Mcg128Xsl64>(1, &DEFAULT_PARAMS, &input, budget, &config);
+        assert_eq!(score, again);
+    }
+
+    #[test]
+    fn test_tempering_acceptance() {
+        let points = vec![(100, 100), (5000, 5000), (9000, 1000), (5100, 5100)];
+        let input = Input {
+            rects: points
+                .iter()
+                .map(|&(x, y)| Rect::new(x, x + 1, y, y + 1))
+                .collect(),
+            points,
+            sizes: vec![1_000_000, 2_000_000, 500_000, 800_000],
+        };
+        let config = Tempering::geometric(2, 2.0, 10_000);
+        let budget = Budget::Iterations(40_000);
+        let (metropolis, _, _) =
+            tempering::<Mcg128Xsl64>(1, &DEFAULT_PARAMS, &input, budget, &config);
+        let mut params = DEFAULT_PARAMS;
+        params.acceptance = Acceptance::LateAcceptance { length: 50 };
+        let (score, best, stats) = tempering::<Mcg128Xsl64>(1, &params, &input, budget, &config);
+        assert_eq!(stats.all, 80_000);
+        // 判定が変われば同じ seed でも結果が変わる
+        assert_ne!(score, metropolis);
+        for (i, rect) in best.iter().enumerate() {
+            assert!(rect.contain(input.points[i].0, input.points[i].1));
+            assert!(best[i + 1..].iter().all(|other| !rect.intersect(other)));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use super::{Input, Rect, State};
+
+/// State::begin から commit / rollback までに変えた長方形
+pub(crate) struct Transaction {
+    /// (i, 変える前の値) を変えた順に
+    undo: Vec<(usize, Rect)>,
+    /// begin したときの全体のスコア
+    score: f64,
+}
+
+impl Transaction {
+    pub(crate) fn record(&mut self, i: usize, old: &Rect) {
+        self.undo.push((i, old.clone()));
+    }
+}
+
+/// old から new にしたときに新しく覆う部分（最大4つの長方形）
+/// 重ならなければ new そのもの
+fn grown_parts(old: &Rect, new: &Rect) -> Vec<Rect> {
+    if !old.intersect(new) {
+        return vec![new.clone()];
+    }
+    let mut parts = Vec::with_capacity(4);
+    if new.x1 < old.x1 {
+        parts.push(Rect::new(new.x1, old.x1, new.y1, new.y2));
+    }
+    if old.x2 < new.x2 {
+        parts.push(Rect::new(old.x2, new.x2, new.y1, new.y2));
+    }
+    let (x1, x2) = (new.x1.max(old.x1), new.x2.min(old.x2));
+    if new.y1 < old.y1 {
+        parts.push(Rect::new(x1, x2, new.y1, old.y1));
+    }
+    if old.y2 < new.y2 {
+        parts.push(Rect::new(x1, x2, old.y2, new.y2));
+    }
+    parts
+}
+
+impl State {
+    /// 複数の長方形を動かす変更を始める
+    /// commit か rollback までの set はすべて戻せる
+    pub(crate) fn begin(&mut self) {
+        debug_assert!(self.tx.is_none(), "transaction is already open");
+        self.tx = Some(Transaction {
+            undo: Vec::new(),
+            score: self.score,
+        });
+    }
+
+    /// i 番目を rect にする
+    /// begin していないか、点を含まないか、bounds の外に出るか、
+    /// （それまでに apply したものも含めて）他と重なるなら何もせず false
+    pub(crate) fn apply(&mut self, i: usize, rect: Rect, input: &Input) -> bool {
+        if self.tx.is_none() {
+            return false;
+        }
+        let (x, y) = input.points[i];
+        if !rect.contain(x, y) {
+            return false;
+        }
+        let b = &self.bounds;
+        if rect.x1 < b.x1 || b.x2 < rect.x2 || rect.y1 < b.y1 || b.y2 < rect.y2 {
+            return false;
+        }
+        if grown_parts(&self.rects[i], &rect)
+            .iter()
+            .any(|part| self.qtree.intersect(part, &self.rects))
+        {
+            return false;
+        }
+        self.set(i, rect, &input.sizes);
+        true
+    }
+
+    /// 変更を確定する
+    /// begin してからのスコアの差を返す
+    pub(crate) fn commit(&mut self) -> f64 {
+        let tx = self.tx.take().expect("commit outside a transaction");
+        self.score - tx.score
+    }
+
+    /// begin したときの状態に戻す
+    pub(crate) fn rollback(&mut self, sizes: &[i32]) {
+        let tx = self.tx.take().expect("rollback outside a transaction");
+        for (i, rect) in tx.undo.into_iter().rev() {
+            self.set(i, rect, sizes);
+        }
+        // 浮動小数点の誤差を持ち込まない
+        self.score = tx.score;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proconio::source::once::OnceSource;
+
+    use super::super::{calc_score, get_gid, guillotine, parse_source};
+    use super::*;
+
+    #[test]
+    fn test_transaction() {
+        let input = parse_source(OnceSource::new(
+            include_str!("../../tools/in/0000.txt").as_bytes(),
+        ));
+        let rects = guillotine(&input.points, &input.sizes);
+        let before = calc_score(&rects, &input.sizes).0;
+        let mut state = State::new(rects.clone(), &input.sizes);
+        let (a, b) = (0, 1);
+        let (xa, ya) = input.points[a];
+        let (xb, yb) = input.points[b];
+
+        // begin していなければ何もしない
+        assert!(!state.apply(a, Rect::new(xa, xa + 1, ya, ya + 1), &input));
+        assert_eq!(state.score, before);
+
+        // a を縮めてから b を広げる2段の変更で、2段目が失敗したら戻す
+        state.begin();
+        assert!(state.apply(a, Rect::new(xa, xa + 1, ya, ya + 1), &input));
+        // a の点まで覆うと a と重なる
+        let bad = Rect::new(xa.min(xb), xa.max(xb) + 1, ya.min(yb), ya.max(yb) + 1);
+        assert!(!state.apply(b, bad, &input));
+        state.rollback(&input.sizes);
+        assert_eq!(state.score, before);
+        for (i, rect) in state.rects.iter().enumerate() {
+            assert_eq!(
+                (rect.x1, rect.x2, rect.y1, rect.y2),
+                (rects[i].x1, rects[i].x2, rects[i].y1, rects[i].y2)
+            );
+            assert!(state.qtree.grid[get_gid(rect) as usize].contains(&(i as u8)));
+        }
+
+        state.begin();
+        assert!(state.apply(a, Rect::new(xa, xa + 1, ya, ya + 1), &input));
+        assert!(state.apply(b, Rect::new(xb, xb + 1, yb, yb + 1), &input));
+        let diff = state.commit();
+        let after = calc_score(&state.rects, &input.sizes).0;
+        assert!((state.score - after).abs() < 1e-9);
+        assert!((diff - (after - before)).abs() < 1e-9);
+
+        // 領域の外には出さない
+        state.bounds = Rect::new(xa, xa + 1, ya, ya + 1);
+        state.begin();
+        assert!(state.apply(a, Rect::new(xa, xa + 1, ya, ya + 1), &input));
+        assert!(!state.apply(a, Rect::new(xa, xa + 2, ya, ya + 1), &input));
+        state.rollback(&input.sizes);
+    }
+}
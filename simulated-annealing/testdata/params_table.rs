@@ -0,0 +1,42 @@
+pub const PARAMS_TABLE: super::table::ParamsTable = super::table::ParamsTable {
+    interpolate: true,
+    entries: std::borrow::Cow::Borrowed(PARAMS_ENTRIES),
+};
+const PARAMS_ENTRIES: &[super::table::ParamsEntry] = &[
+    super::table::ParamsEntry {
+        n: 50,
+        params: super::params::McParams {
+            temp0: 12.5,
+            temp1: 0.00029342425784192465,
+            slide_d: super::schedule::Schedule::Log { start: 30.0, end: 1.5 },
+            grow_d1: super::schedule::Schedule::Piecewise { knots: std::borrow::Cow::Borrowed(&[(0.0, 8.0), (0.25, 4.0), (1.0, 1.0)]) },
+            grow_d2: super::schedule::Schedule::Linear { start: 1532.4395218778254, end: 3.178973798285788 },
+            grow_d3: super::schedule::Schedule::Linear { start: 660.2448737846898, end: 5.668514832161116 },
+            weight_slide: super::schedule::Schedule::Linear { start: 0.056353565358393205, end: 0.056353565358393205 },
+            weight_d1: super::schedule::Schedule::Linear { start: 0.1745393427756753, end: 0.1745393427756753 },
+            weight_d2: super::schedule::Schedule::Linear { start: 0.7691070918659315, end: 0.7691070918659315 },
+            weight_d3: super::schedule::Schedule::Linear { start: 0.0, end: 0.0 },
+            stagnation: Some(super::params::Stagnation { window: 5000, action: super::params::StagnationAction::Compact { toward: super::compact::Toward::High } }),
+            lns: Some(super::params::Lns { interval: 4000, size: 1200, repair: 300 }),
+            acceptance: super::acceptance::Acceptance::GreatDeluge { rain: 1e-7 },
+        },
+    },
+    super::table::ParamsEntry {
+        n: 150,
+        params: super::params::McParams {
+            temp0: 0.10868564634648839,
+            temp1: 0.00029342425784192465,
+            slide_d: super::schedule::Schedule::Linear { start: 59.0, end: 59.0 },
+            grow_d1: super::schedule::Schedule::Linear { start: 661.4780032749206, end: 7.211273402804876 },
+            grow_d2: super::schedule::Schedule::Power { start: 20.0, end: 2.0, exponent: 1.5 },
+            grow_d3: super::schedule::Schedule::Linear { start: 660.2448737846898, end: 5.668514832161116 },
+            weight_slide: super::schedule::Schedule::Linear { start: 0.056353565358393205, end: 0.056353565358393205 },
+            weight_d1: super::schedule::Schedule::Linear { start: 0.1745393427756753, end: 0.1745393427756753 },
+            weight_d2: super::schedule::Schedule::Linear { start: 0.7691070918659315, end: 0.7691070918659315 },
+            weight_d3: super::schedule::Schedule::Linear { start: 0.0, end: 0.0 },
+            stagnation: Some(super::params::Stagnation { window: 10000, action: super::params::StagnationAction::Reheat { fraction: 0.5 } }),
+            lns: None,
+            acceptance: super::acceptance::Acceptance::Metropolis,
+        },
+    },
+];
@@ -0,0 +1,41 @@
+use proconio::source::once::OnceSource;
+
+use simulated_annealing::{
+    atcoder_iterations, calibrate, parse_source, ATCODER_RATE, PARAMS_TABLE,
+};
+
+/// ATCODER_RATE を測ったのと同じ入力
+const REFERENCE: &str = include_str!("../../tools/in/0001.txt");
+
+const USAGE: &str = "Usage: calibrate [--millis MS] [--limit MS]";
+
+fn main() {
+    let mut millis = 1000;
+    let mut limit = 4970;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().and_then(|v| v.parse().ok());
+        match (arg.as_str(), value) {
+            ("--millis", Some(v)) => millis = v,
+            ("--limit", Some(v)) => limit = v,
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(1)
+            }
+        }
+    }
+
+    let input = parse_source(OnceSource::new(REFERENCE.as_bytes()));
+    let params = PARAMS_TABLE.select(input.rects.len());
+    let rate = calibrate(&input, &params, millis);
+    let ratio = rate / ATCODER_RATE;
+    println!("local:   {:.0} proposals/s", rate);
+    println!("AtCoder: {:.0} proposals/s", ATCODER_RATE);
+    println!("ratio:   {:.3}", ratio);
+    println!(
+        "{} ms on AtCoder = {} iterations = {:.0} ms here",
+        limit,
+        atcoder_iterations(limit),
+        limit as f64 / ratio
+    );
+}
@@ -0,0 +1,17 @@
+use simulated_annealing::ParamsTable;
+
+/// パラメータテーブルの JSON を読んで、提出用に埋め込む `PARAMS_TABLE` の定義を出力する
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Usage: embed <params-table.json>");
+            std::process::exit(1)
+        }
+    };
+    let table = ParamsTable::load(&path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
+    print!("{}", table.to_rust());
+}
@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+
+use ahc001::eval::load_instances;
+use simulated_annealing::upper_bound;
+
+const USAGE: &str = "Usage: gap [--scores <path>] [--dir <path>]";
+
+/// scores.txt の各行（seed スコア）を入力から求めた上界と比べる
+fn main() -> Result<()> {
+    let mut scores_path = PathBuf::from("scores.txt");
+    let mut dir = PathBuf::from("tools/in");
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().context(USAGE);
+        match arg.as_str() {
+            "--scores" => scores_path = PathBuf::from(value()?),
+            "--dir" => dir = PathBuf::from(value()?),
+            _ => bail!(USAGE),
+        }
+    }
+
+    let text = std::fs::read_to_string(&scores_path)
+        .with_context(|| format!("cannot read {}", scores_path.display()))?;
+    let mut rows = Vec::new();
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        let (seed, score) = match (fields.next(), fields.next()) {
+            (Some(seed), Some(score)) => (seed.parse::<u32>()?, score.parse::<f64>()?),
+            _ => bail!("invalid line in {}: {}", scores_path.display(), line),
+        };
+        rows.push((seed, score));
+    }
+    let paths = rows
+        .iter()
+        .map(|(seed, _)| dir.join(format!("{:04}.txt", seed)))
+        .collect::<Vec<_>>();
+    let instances = load_instances(&paths)?;
+
+    println!("seed score bound gap");
+    let (mut score_sum, mut bound_sum) = (0.0, 0.0);
+    let mut tight = 0;
+    for ((seed, score), instance) in rows.iter().zip(instances.iter()) {
+        let bound = upper_bound(&instance.input).score;
+        if bound < 1.0 {
+            tight += 1;
+        }
+        score_sum += score;
+        bound_sum += bound;
+        println!("{} {:.6} {:.6} {:.6}", seed, score, bound, bound - score);
+    }
+    let n = rows.len() as f64;
+    eprintln!(
+        "mean score {:.6}, mean bound {:.6}, mean gap {:.6}, bound below 1: {}/{}",
+        score_sum / n,
+        bound_sum / n,
+        (bound_sum - score_sum) / n,
+        tight,
+        rows.len()
+    );
+    Ok(())
+}
@@ -4,7 +4,7 @@ use anyhow::{Context, Result};
 use proconio::source::once::OnceSource;
 use serde::{Deserialize, Serialize};
 
-use simulated_annealing::{parse_source, run};
+use simulated_annealing::{parse_source, run_with_config, RunConfig};
 use tokio::io::AsyncReadExt;
 
 async fn next_invocation(url_base: &str) -> Result<(String, String)> {
@@ -35,12 +35,18 @@ struct Body {
     message_id: String,
     seed: u32,
     arg: Option<String>,
+    /// 焼きなましの乱数の seed（入力の seed とは別）
+    rng_seed: Option<u64>,
+    /// ミリ秒
+    limit: Option<u64>,
+    iterations: Option<u64>,
 }
 
 #[derive(Serialize)]
 struct Response {
     message_id: String,
     score: f64,
+    config: RunConfig,
 }
 
 async fn calc(data: &str) -> Result<Response> {
@@ -54,10 +60,21 @@ async fn calc(data: &str) -> Result<Response> {
     let source = OnceSource::new(buf.as_bytes());
     let input = parse_source(source);
 
-    let (score, _) = run(input, body.arg);
+    let mut config = RunConfig::new(body.arg, input.rects.len())?;
+    if let Some(seed) = body.rng_seed {
+        config.seed = seed;
+    }
+    if let Some(limit) = body.limit {
+        config.limit = limit;
+    }
+    if body.iterations.is_some() {
+        config.iterations = body.iterations;
+    }
+    let (score, _) = run_with_config(&input, &config);
     Ok(Response {
         message_id: body.message_id,
         score,
+        config,
     })
 }
 
@@ -1,17 +1,181 @@
 use proconio::source::once::OnceSource;
 
-use simulated_annealing::{parse_source, run};
+use simulated_annealing::{
+    free_space, parse_source, run_with_stats, Adjacency, Decompose, Init, Memetic, Portfolio,
+    RunConfig, Tabu, Tempering,
+};
+
+fn usage() -> ! {
+    eprintln!("Usage: local [--sidecar <path>] [--config <path>] [--iterations N] [--replicas N [--ratio R] [--interval N]] [--starts K [--rounds R]] [--population N] [--tabu K [--tenure N]] [--regions K [--polish F]] [--init unit|guillotine] [--adjacency] [--free] [params]");
+    std::process::exit(1)
+}
 
 fn main() {
+    let mut params = None;
+    let mut sidecar = None;
+    let mut config_path = None;
+    let mut iterations = None;
+    let mut replicas = None;
+    let mut ratio = 1.5;
+    let mut interval = 20_000;
+    let mut starts = None;
+    let mut rounds = 0;
+    let mut init = None;
+    let mut population = None;
+    let mut tabu = None;
+    let mut tenure = Tabu::default().tenure;
+    let mut regions = None;
+    let mut polish = 0.1;
+    let mut adjacency = false;
+    let mut free = false;
     let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sidecar" => sidecar = Some(args.next().unwrap_or_else(|| usage())),
+            "--config" => config_path = Some(args.next().unwrap_or_else(|| usage())),
+            "--iterations" => {
+                let n = args.next().and_then(|n| n.parse().ok());
+                iterations = Some(n.unwrap_or_else(|| usage()))
+            }
+            "--replicas" => {
+                let n = args.next().and_then(|n| n.parse().ok());
+                replicas = Some(n.unwrap_or_else(|| usage()))
+            }
+            "--ratio" => {
+                let r = args.next().and_then(|r| r.parse().ok());
+                ratio = r.unwrap_or_else(|| usage())
+            }
+            "--interval" => {
+                let n = args.next().and_then(|n| n.parse().ok());
+                interval = n.unwrap_or_else(|| usage())
+            }
+            "--starts" => {
+                let n = args.next().and_then(|n| n.parse().ok());
+                starts = Some(n.unwrap_or_else(|| usage()))
+            }
+            "--population" => {
+                let n = args.next().and_then(|n| n.parse().ok());
+                population = Some(n.unwrap_or_else(|| usage()))
+            }
+            "--tabu" => {
+                let n = args.next().and_then(|n| n.parse().ok());
+                tabu = Some(n.unwrap_or_else(|| usage()))
+            }
+            "--tenure" => {
+                let n = args.next().and_then(|n| n.parse().ok());
+                tenure = n.unwrap_or_else(|| usage())
+            }
+            "--init" => {
+                init = match args.next().as_deref() {
+                    Some("unit") => Some(Init::Unit),
+                    Some("guillotine") => Some(Init::Guillotine),
+                    _ => usage(),
+                }
+            }
+            "--regions" => {
+                let n = args.next().and_then(|n| n.parse().ok());
+                regions = Some(n.unwrap_or_else(|| usage()))
+            }
+            "--polish" => {
+                let f = args.next().and_then(|f| f.parse().ok());
+                polish = f.unwrap_or_else(|| usage())
+            }
+            "--adjacency" => adjacency = true,
+            "--free" => free = true,
+            "--rounds" => {
+                let n = args.next().and_then(|n| n.parse().ok());
+                rounds = n.unwrap_or_else(|| usage())
+            }
+            _ if params.is_none() => params = Some(arg),
+            _ => usage(),
+        }
+    }
+
     let stdin = std::io::stdin();
     let f = stdin.lock();
     // let f = std::io::BufReader::new(std::fs::File::open("./tools/in/0001.txt").unwrap());
     let source = OnceSource::new(f);
     let input = parse_source(source);
+    let n = input.rects.len();
 
-    let (_, best) = run(input, args.next());
+    let mut config = match config_path {
+        Some(path) => RunConfig::load(&path),
+        None => RunConfig::new(params, n),
+    }
+    .unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    });
+    if iterations.is_some() {
+        config.iterations = iterations;
+    }
+    if let Some(replicas) = replicas {
+        config.tempering = Some(Tempering::geometric(replicas, ratio, interval));
+    }
+    if let Some(init) = init {
+        config.init = init;
+    }
+    if let Some(starts) = starts {
+        config.portfolio = Some(Portfolio {
+            starts,
+            params: Vec::new(),
+            rounds,
+        });
+    }
+    if let Some(population) = population {
+        config.memetic = Some(Memetic {
+            population,
+            init: 500_000,
+            local: 200_000,
+            local_start: 0.7,
+        });
+    }
+    if let Some(candidates) = tabu {
+        config.tabu = Some(Tabu {
+            candidates,
+            tenure,
+            ..Tabu::default()
+        });
+    }
+    if let Some(regions) = regions {
+        config.decompose = Some(Decompose { regions, polish });
+    }
+    if adjacency {
+        config.adjacency = true;
+    }
+    if let Err(e) = config.validate() {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    }
+    let (score, best, stats) = run_with_stats(&input, &config);
+    if config.adjacency {
+        // 途中の推移は今の配置、最後の行は最良解
+        for (at, boxed_in) in stats.boxed_in.iter() {
+            eprintln!("{} {}", at, boxed_in);
+        }
+        let adjacency = Adjacency::new(&best);
+        eprintln!(
+            "contacts: {}, boxed in: {}",
+            adjacency.pairs().count(),
+            adjacency.boxed_in(&best)
+        );
+    }
+    if free {
+        let free = free_space(&best);
+        eprintln!(
+            "free: {} ({:.4}), maximal empty rects: {}",
+            free.area,
+            free.ratio(),
+            free.rects.len()
+        );
+    }
     for rect in best {
         println!("{} {} {} {}", rect.x1, rect.y1, rect.x2, rect.y2);
     }
+    if let Some(path) = sidecar {
+        if let Err(e) = config.save(&path, n, score) {
+            eprintln!("cannot write {}: {}", path, e);
+            std::process::exit(1)
+        }
+    }
 }
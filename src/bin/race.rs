@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+use ahc001::{
+    eval::{default_threads, load_instances},
+    race::{race, Candidate, RaceConfig},
+    tune::Study,
+};
+use simulated_annealing::{atcoder_iterations, McParams, DEFAULT_PARAMS};
+
+const USAGE: &str = "Usage: race [--candidate <params>]... [--study <path> --top N] [--block N] [--min-blocks N] [--alpha F] [--limit MS] [--iterations N | --equivalent] [--threads N] [--seed N] <instance>...";
+
+fn main() -> Result<()> {
+    let mut candidates = Vec::new();
+    let mut study_path = None;
+    let mut top = 0;
+    let mut instance_paths = Vec::new();
+    let mut config = RaceConfig {
+        block: 10,
+        min_blocks: 2,
+        alpha: 0.05,
+        seed: 1,
+        limit: 500,
+        iterations: None,
+        threads: default_threads(),
+    };
+    let mut equivalent = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().context(USAGE);
+        match arg.as_str() {
+            "--candidate" => {
+                let source = value()?;
+                let mut params = DEFAULT_PARAMS;
+                params.merge(&source, "--candidate")?;
+                params.validate()?;
+                candidates.push(Candidate {
+                    name: source,
+                    params,
+                });
+            }
+            "--study" => study_path = Some(PathBuf::from(value()?)),
+            "--top" => top = value()?.parse()?,
+            "--block" => config.block = value()?.parse()?,
+            "--min-blocks" => config.min_blocks = value()?.parse()?,
+            "--alpha" => config.alpha = value()?.parse()?,
+            "--limit" => config.limit = value()?.parse()?,
+            "--iterations" => config.iterations = Some(value()?.parse()?),
+            "--equivalent" => equivalent = true,
+            "--threads" => config.threads = value()?.parse()?,
+            "--seed" => config.seed = value()?.parse()?,
+            _ if arg.starts_with("--") => bail!(USAGE),
+            _ => instance_paths.push(PathBuf::from(arg)),
+        }
+    }
+    if let Some(path) = study_path {
+        let study = Study::open(&path)?;
+        let mut trials = study
+            .trials
+            .iter()
+            .filter(|t| t.error.is_none())
+            .collect::<Vec<_>>();
+        trials.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        for trial in trials.into_iter().take(top) {
+            let params: McParams = serde_json::from_value(Value::Object(trial.params.clone()))?;
+            candidates.push(Candidate {
+                name: format!("trial {}", trial.id),
+                params,
+            });
+        }
+    }
+    if candidates.len() < 2 || instance_paths.is_empty() {
+        bail!(USAGE);
+    }
+    if equivalent {
+        config.iterations = Some(atcoder_iterations(config.limit));
+    }
+    let instances = load_instances(&instance_paths)?;
+
+    let result = race(&candidates, &instances, &config, |entries| {
+        let done = entries.iter().map(|e| e.scores.len()).max().unwrap_or(0);
+        let alive = entries.iter().filter(|e| e.eliminated.is_none()).count();
+        eprintln!("{:4} instances: {} candidates alive", done, alive);
+    });
+
+    let full = candidates.len() * instances.len();
+    eprintln!(
+        "{} runs ({:.1}% of {})",
+        result.runs,
+        100.0 * result.runs as f64 / full as f64,
+        full
+    );
+    for entry in result.entries.iter() {
+        let status = match entry.eliminated {
+            Some(n) => format!("eliminated after {}", n),
+            None => "alive".to_owned(),
+        };
+        eprintln!("{:.6} {:>24} {}", entry.mean(), status, entry.name);
+    }
+    let winner = result.winner();
+    println!(
+        "{}",
+        serde_json::to_string(&candidates[winner].params).context("cannot serialize params")?
+    );
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+
+use simulated_annealing::atcoder_iterations;
+
+use ahc001::{
+    eval::{default_threads, load_instances},
+    tune::{default_space, load_space, tune, Study, TuneConfig},
+};
+
+const USAGE: &str = "Usage: tune --study <path> [--trials N] [--init N] [--limit MS] [--iterations N | --equivalent] [--threads N] [--seed N] [--sigma F] [--space <path>] <instance>...";
+
+fn main() -> Result<()> {
+    let mut study_path = None;
+    let mut space_path = None;
+    let mut instance_paths = Vec::new();
+    let mut config = TuneConfig {
+        space: Vec::new(),
+        trials: 200,
+        init: 20,
+        seed: 1,
+        sigma: 0.2,
+        limit: 500,
+        iterations: None,
+        threads: default_threads(),
+    };
+    let mut equivalent = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().context(USAGE);
+        match arg.as_str() {
+            "--study" => study_path = Some(PathBuf::from(value()?)),
+            "--space" => space_path = Some(PathBuf::from(value()?)),
+            "--trials" => config.trials = value()?.parse()?,
+            "--init" => config.init = value()?.parse()?,
+            "--limit" => config.limit = value()?.parse()?,
+            "--iterations" => config.iterations = Some(value()?.parse()?),
+            "--equivalent" => equivalent = true,
+            "--threads" => config.threads = value()?.parse()?,
+            "--seed" => config.seed = value()?.parse()?,
+            "--sigma" => config.sigma = value()?.parse()?,
+            _ if arg.starts_with("--") => bail!(USAGE),
+            _ => instance_paths.push(PathBuf::from(arg)),
+        }
+    }
+    let study_path = study_path.context(USAGE)?;
+    if instance_paths.is_empty() {
+        bail!(USAGE);
+    }
+    config.space = match space_path {
+        Some(path) => load_space(&path)?,
+        None => default_space(),
+    };
+    if equivalent {
+        config.iterations = Some(atcoder_iterations(config.limit));
+    }
+    let instances = load_instances(&instance_paths)?;
+    let mut study = Study::open(&study_path)?;
+    eprintln!(
+        "{} instances, {} trials done",
+        instances.len(),
+        study.trials.len()
+    );
+    tune(&mut study, &instances, &config)?;
+    if let Some(best) = study.best() {
+        eprintln!("best: trial {} score {:.6}", best.id, best.score);
+        println!("{}", serde_json::to_string(&best.params)?);
+    }
+    Ok(())
+}
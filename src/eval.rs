@@ -0,0 +1,88 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use proconio::source::once::OnceSource;
+
+use simulated_annealing::{parse_source, run_with_config, Input, RunConfig};
+
+pub struct Instance {
+    pub name: String,
+    pub input: Input,
+}
+
+fn read_instance(path: &Path) -> Result<Instance> {
+    let f = File::open(path).with_context(|| format!("cannot open {}", path.display()))?;
+    let input = parse_source(OnceSource::new(BufReader::new(f)));
+    Ok(Instance {
+        name: path.display().to_string(),
+        input,
+    })
+}
+
+/// ファイルはそのまま、ディレクトリは直下の *.txt をすべて読む
+pub fn load_instances<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<Instance>> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        if path.is_dir() {
+            let mut entries = path
+                .read_dir()
+                .with_context(|| format!("cannot read {}", path.display()))?
+                .map(|e| e.map(|e| e.path()))
+                .collect::<std::io::Result<Vec<PathBuf>>>()?;
+            entries.retain(|p| p.extension().is_some_and(|ext| ext == "txt"));
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.to_owned());
+        }
+    }
+    files.iter().map(|p| read_instance(p)).collect()
+}
+
+pub fn default_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// items を threads 本のスレッドで f にかける
+/// 結果は items と同じ順
+pub fn par_map<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<Option<R>>>());
+    std::thread::scope(|s| {
+        for _ in 0..threads.max(1).min(items.len()) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= items.len() {
+                    break;
+                }
+                let r = f(&items[i]);
+                results.lock().unwrap()[i] = Some(r);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.unwrap())
+        .collect()
+}
+
+/// 各インスタンスでのスコア（0〜1）
+pub fn evaluate(instances: &[Instance], config: &RunConfig, threads: usize) -> Vec<f64> {
+    par_map(instances, threads, |instance| {
+        run_with_config(&instance.input, config).0
+    })
+}
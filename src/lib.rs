@@ -0,0 +1,6 @@
+//! パラメータ探索のための道具
+//! 提出するのは simulated-annealing と src/bin/local.rs だけ
+
+pub mod eval;
+pub mod race;
+pub mod tune;
@@ -0,0 +1,281 @@
+use simulated_annealing::{run_with_config, McParams, RunConfig};
+
+use crate::eval::{par_map, Instance};
+
+pub struct Candidate {
+    pub name: String,
+    pub params: McParams,
+}
+
+pub struct RaceConfig {
+    /// 1ブロックで解くインスタンス数
+    pub block: usize,
+    /// 検定を始めるまでのブロック数
+    pub min_blocks: usize,
+    /// 有意水準（Holm 法で多重比較を補正する）
+    pub alpha: f64,
+    pub seed: u64,
+    /// 1回の実行のミリ秒
+    pub limit: u64,
+    /// Some なら時間の代わりに提案回数で打ち切る
+    pub iterations: Option<u64>,
+    pub threads: usize,
+}
+
+pub struct RaceEntry {
+    pub name: String,
+    /// 解いたインスタンスのスコア（instances の先頭から順）
+    pub scores: Vec<f64>,
+    /// 脱落したときのインスタンス数
+    pub eliminated: Option<usize>,
+}
+
+impl RaceEntry {
+    pub fn mean(&self) -> f64 {
+        self.scores.iter().sum::<f64>() / self.scores.len() as f64
+    }
+}
+
+pub struct RaceResult {
+    pub entries: Vec<RaceEntry>,
+    /// 実際に解いた回数
+    pub runs: usize,
+}
+
+impl RaceResult {
+    /// 生き残った中で平均スコアが最大のもの
+    pub fn winner(&self) -> usize {
+        (0..self.entries.len())
+            .filter(|&c| self.entries[c].eliminated.is_none())
+            .max_by(|&a, &b| {
+                self.entries[a]
+                    .mean()
+                    .partial_cmp(&self.entries[b].mean())
+                    .unwrap()
+            })
+            .unwrap()
+    }
+}
+
+/// F-race 風のレース
+///
+/// instances を先頭から block 個ずつ、生き残っている候補すべてで解く。
+/// min_blocks 以降は各ブロックの後で、平均スコア最大の候補と他の候補を
+/// インスタンスごとの対応のある t 検定（片側）で比べ、有意に劣るものを落とす。
+/// 候補が1つになるかインスタンスを使い切ったら終わり。
+pub fn race<F>(
+    candidates: &[Candidate],
+    instances: &[Instance],
+    config: &RaceConfig,
+    mut on_block: F,
+) -> RaceResult
+where
+    F: FnMut(&[RaceEntry]),
+{
+    let mut entries = candidates
+        .iter()
+        .map(|c| RaceEntry {
+            name: c.name.clone(),
+            scores: Vec::new(),
+            eliminated: None,
+        })
+        .collect::<Vec<_>>();
+    let mut runs = 0;
+    let block = config.block.max(1);
+    let mut start = 0;
+    let mut blocks = 0;
+    while start < instances.len() {
+        let end = (start + block).min(instances.len());
+        let alive = (0..entries.len())
+            .filter(|&c| entries[c].eliminated.is_none())
+            .collect::<Vec<_>>();
+        let jobs = alive
+            .iter()
+            .flat_map(|&c| (start..end).map(move |i| (c, i)))
+            .collect::<Vec<_>>();
+        let results = par_map(&jobs, config.threads, |&(c, i)| {
+            let run_config = RunConfig::with_params(
+                candidates[c].params.clone(),
+                config.seed,
+                config.limit,
+                config.iterations,
+            );
+            run_with_config(&instances[i].input, &run_config).0
+        });
+        runs += jobs.len();
+        for (&(c, _), score) in jobs.iter().zip(results) {
+            entries[c].scores.push(score);
+        }
+        start = end;
+        blocks += 1;
+
+        if blocks >= config.min_blocks && alive.len() > 1 {
+            for c in eliminate(&entries, &alive, config.alpha) {
+                entries[c].eliminated = Some(end);
+            }
+        }
+        on_block(&entries);
+        if entries.iter().filter(|e| e.eliminated.is_none()).count() <= 1 {
+            break;
+        }
+    }
+    RaceResult { entries, runs }
+}
+
+/// 最良の候補に有意に劣る候補
+fn eliminate(entries: &[RaceEntry], alive: &[usize], alpha: f64) -> Vec<usize> {
+    let best = *alive
+        .iter()
+        .max_by(|&&a, &&b| entries[a].mean().partial_cmp(&entries[b].mean()).unwrap())
+        .unwrap();
+    let mut p_values = alive
+        .iter()
+        .filter(|&&c| c != best)
+        .map(|&c| (paired_t_test(&entries[best].scores, &entries[c].scores), c))
+        .collect::<Vec<_>>();
+    p_values.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    // Holm 法
+    let m = p_values.len();
+    p_values
+        .iter()
+        .enumerate()
+        .take_while(|(j, (p, _))| *p <= alpha / (m - j) as f64)
+        .map(|(_, &(_, c))| c)
+        .collect()
+}
+
+/// a の方が大きいという片側検定の p 値
+pub fn paired_t_test(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len();
+    if n < 2 {
+        return 1.0;
+    }
+    let d = a
+        .iter()
+        .zip(b.iter())
+        .map(|(a, b)| a - b)
+        .collect::<Vec<_>>();
+    let mean = d.iter().sum::<f64>() / n as f64;
+    let var = d.iter().map(|d| (d - mean) * (d - mean)).sum::<f64>() / (n - 1) as f64;
+    if var == 0.0 {
+        return if mean > 0.0 { 0.0 } else { 1.0 };
+    }
+    let t = mean / (var / n as f64).sqrt();
+    t_sf(t, (n - 1) as f64)
+}
+
+/// 自由度 df の t 分布で T > t となる確率
+pub fn t_sf(t: f64, df: f64) -> f64 {
+    let tail = 0.5 * beta_inc(df / 2.0, 0.5, df / (df + t * t));
+    if t > 0.0 {
+        tail
+    } else {
+        1.0 - tail
+    }
+}
+
+fn ln_gamma(x: f64) -> f64 {
+    // Lanczos 近似
+    const G: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let mut ser = 1.000000000190015;
+    for (j, g) in G.iter().enumerate() {
+        ser += g / (x + 1.0 + j as f64);
+    }
+    -tmp + (2.5066282746310005 * ser / x).ln()
+}
+
+/// 正則化不完全ベータ関数 I_x(a, b)
+fn beta_inc(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_cf(a, b, x) / a
+    } else {
+        1.0 - front * beta_cf(b, a, 1.0 - x) / b
+    }
+}
+
+/// 連分数展開 (Numerical Recipes の betacf)
+fn beta_cf(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..200 {
+        let m = m as f64;
+        let aa = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        h *= d * c;
+        let aa = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let del = d * c;
+        h *= del;
+        if (del - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_t_sf() {
+        // t 分布表の値
+        assert!((t_sf(2.228, 10.0) - 0.025).abs() < 1e-4);
+        assert!((t_sf(1.812, 10.0) - 0.05).abs() < 1e-4);
+        assert!((t_sf(-1.812, 10.0) - 0.95).abs() < 1e-4);
+        assert!((t_sf(0.0, 3.0) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_eliminate() {
+        let entry = |scores: Vec<f64>| RaceEntry {
+            name: String::new(),
+            scores,
+            eliminated: None,
+        };
+        let entries = vec![
+            entry(vec![0.95, 0.96, 0.97, 0.95, 0.96, 0.94]),
+            entry(vec![0.90, 0.91, 0.92, 0.90, 0.91, 0.89]),
+            entry(vec![0.96, 0.95, 0.96, 0.96, 0.95, 0.94]),
+        ];
+        assert_eq!(eliminate(&entries, &[0, 1, 2], 0.05), vec![1]);
+    }
+}
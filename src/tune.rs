@@ -0,0 +1,508 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use rand::Rng;
+use rand_pcg::Mcg128Xsl64;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use simulated_annealing::{McParams, RunConfig, DEFAULT_PARAMS};
+
+use crate::eval::{evaluate, Instance};
+
+/// 探索空間の1次元
+/// x ∈ [0, 1] を [low, high] に（log なら対数スケールで）写す
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dim {
+    pub name: String,
+    pub low: f64,
+    pub high: f64,
+    #[serde(default)]
+    pub log: bool,
+}
+
+impl Dim {
+    fn new(name: &str, low: f64, high: f64, log: bool) -> Dim {
+        Dim {
+            name: name.to_owned(),
+            low,
+            high,
+            log,
+        }
+    }
+
+    pub fn decode(&self, x: f64) -> f64 {
+        let x = x.clamp(0.0, 1.0);
+        if self.log {
+            self.low * (self.high / self.low).powf(x)
+        } else {
+            self.low + (self.high - self.low) * x
+        }
+    }
+
+    pub fn encode(&self, v: f64) -> f64 {
+        let x = if self.log {
+            (v / self.low).ln() / (self.high / self.low).ln()
+        } else {
+            (v - self.low) / (self.high - self.low)
+        };
+        x.clamp(0.0, 1.0)
+    }
+}
+
+/// opt.py と同じ探索空間
+pub fn default_space() -> Vec<Dim> {
+    vec![
+        Dim::new("temp0", 1e-2, 1.0, true),
+        Dim::new("temp1", 1e-6, 1e-2, true),
+        Dim::new("slide_d_start", 1.0, 2048.0, true),
+        Dim::new("slide_d_end", 1.0, 1024.0, true),
+        Dim::new("grow_d1_start", 1.0, 1024.0, true),
+        Dim::new("grow_d1_end", 1.0, 128.0, true),
+        Dim::new("grow_d2_start", 1.0, 2048.0, true),
+        Dim::new("grow_d2_end", 1.0, 2048.0, true),
+        Dim::new("grow_d3_start", 1.0, 2048.0, true),
+        Dim::new("grow_d3_end", 1.0, 2048.0, true),
+        Dim::new("weight_slide_start", 0.0, 1.0, false),
+        Dim::new("weight_slide_end", 0.0, 1.0, false),
+        Dim::new("weight_d1_start", 0.0, 1.0, false),
+        Dim::new("weight_d1_end", 0.0, 1.0, false),
+        Dim::new("weight_d2_start", 0.0, 1.0, false),
+        Dim::new("weight_d2_end", 0.0, 1.0, false),
+        Dim::new("weight_d3_start", 0.0, 1.0, false),
+        Dim::new("weight_d3_end", 0.0, 1.0, false),
+    ]
+}
+
+pub fn load_space(path: &Path) -> Result<Vec<Dim>> {
+    let f = File::open(path).with_context(|| format!("cannot open {}", path.display()))?;
+    let space: Vec<Dim> = serde_json::from_reader(BufReader::new(f))
+        .with_context(|| format!("cannot parse {}", path.display()))?;
+    for dim in space.iter() {
+        if dim.low >= dim.high || (dim.log && dim.low <= 0.0) {
+            bail!("invalid range for `{}`", dim.name);
+        }
+    }
+    Ok(space)
+}
+
+/// x を McParams の上書き（旧来の平坦な形式）にする
+pub fn decode(space: &[Dim], x: &[f64]) -> Map<String, Value> {
+    space
+        .iter()
+        .zip(x.iter())
+        .map(|(dim, &x)| (dim.name.clone(), Value::from(dim.decode(x))))
+        .collect()
+}
+
+/// McParams の JSON から `grow_d1_start` のような平坦な名前の値を取り出す
+fn flat_value(params: &Value, name: &str) -> Option<f64> {
+    if let Some(v) = params.get(name) {
+        return v.as_f64();
+    }
+    let (field, key) = if let Some(field) = name.strip_suffix("_start") {
+        (field, "start")
+    } else if let Some(field) = name.strip_suffix("_end") {
+        (field, "end")
+    } else {
+        return None;
+    };
+    let schedule = params.get(field)?;
+    if schedule.get("type")?.as_str()? != "linear" {
+        return None;
+    }
+    schedule.get(key)?.as_f64()
+}
+
+/// DEFAULT_PARAMS の位置（表せない次元は中央）
+pub fn default_x(space: &[Dim]) -> Vec<f64> {
+    let params = serde_json::to_value(&DEFAULT_PARAMS).unwrap();
+    space
+        .iter()
+        .map(|dim| flat_value(&params, &dim.name).map_or(0.5, |v| dim.encode(v)))
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trial {
+    pub id: usize,
+    pub x: Vec<f64>,
+    pub params: Map<String, Value>,
+    /// インスタンス平均（不正なパラメータなら 0）
+    pub score: f64,
+    #[serde(default)]
+    pub scores: BTreeMap<String, f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// JSON Lines で1行1試行を追記していくファイル
+pub struct Study {
+    path: PathBuf,
+    pub trials: Vec<Trial>,
+}
+
+impl Study {
+    /// 既にあれば読み込んで続きから
+    pub fn open(path: &Path) -> Result<Study> {
+        let mut trials = Vec::new();
+        if path.exists() {
+            let f = File::open(path).with_context(|| format!("cannot open {}", path.display()))?;
+            for (i, line) in BufReader::new(f).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let trial: Trial = serde_json::from_str(&line)
+                    .with_context(|| format!("{}:{}: broken trial", path.display(), i + 1))?;
+                trials.push(trial);
+            }
+        }
+        Ok(Study {
+            path: path.to_owned(),
+            trials,
+        })
+    }
+
+    pub fn push(&mut self, trial: Trial) -> Result<()> {
+        let mut f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("cannot open {}", self.path.display()))?;
+        writeln!(f, "{}", serde_json::to_string(&trial)?)?;
+        self.trials.push(trial);
+        Ok(())
+    }
+
+    pub fn best(&self) -> Option<&Trial> {
+        self.trials
+            .iter()
+            .max_by(|a, b| a.score.partial_cmp(&b.score).unwrap())
+    }
+}
+
+fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    // Box-Muller
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// 対角共分散の CMA-ES (sep-CMA-ES, Ros & Hansen 2008)
+/// 最小化、探索範囲は [0, 1]^n
+pub struct SepCmaEs {
+    pub lambda: usize,
+    mu: usize,
+    weights: Vec<f64>,
+    mu_eff: f64,
+    c_sigma: f64,
+    d_sigma: f64,
+    c_c: f64,
+    c_1: f64,
+    c_mu: f64,
+    chi_n: f64,
+    generation: usize,
+    pub mean: Vec<f64>,
+    pub sigma: f64,
+    diag: Vec<f64>,
+    p_sigma: Vec<f64>,
+    p_c: Vec<f64>,
+}
+
+impl SepCmaEs {
+    pub fn new(mean: Vec<f64>, sigma: f64) -> SepCmaEs {
+        let n = mean.len() as f64;
+        let lambda = 4 + (3.0 * n.ln()).floor() as usize;
+        let mu = lambda / 2;
+        let raw = (1..=mu)
+            .map(|i| (mu as f64 + 0.5).ln() - (i as f64).ln())
+            .collect::<Vec<_>>();
+        let sum: f64 = raw.iter().sum();
+        let weights = raw.iter().map(|w| w / sum).collect::<Vec<_>>();
+        let mu_eff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+        let c_sigma = (mu_eff + 2.0) / (n + mu_eff + 5.0);
+        let d_sigma = 1.0 + 2.0 * (((mu_eff - 1.0) / (n + 1.0)).sqrt() - 1.0).max(0.0) + c_sigma;
+        let c_c = (4.0 + mu_eff / n) / (n + 4.0 + 2.0 * mu_eff / n);
+        // sep-CMA-ES では学習率を (n + 2) / 3 倍する
+        let c_1 = 2.0 / ((n + 1.3).powi(2) + mu_eff) * (n + 2.0) / 3.0;
+        let c_mu = (2.0 * (mu_eff - 2.0 + 1.0 / mu_eff) / ((n + 2.0).powi(2) + mu_eff) * (n + 2.0)
+            / 3.0)
+            .min(1.0 - c_1);
+        let chi_n = n.sqrt() * (1.0 - 1.0 / (4.0 * n) + 1.0 / (21.0 * n * n));
+        let dim = mean.len();
+        SepCmaEs {
+            lambda,
+            mu,
+            weights,
+            mu_eff,
+            c_sigma,
+            d_sigma,
+            c_c,
+            c_1,
+            c_mu,
+            chi_n,
+            generation: 0,
+            mean,
+            sigma,
+            diag: vec![1.0; dim],
+            p_sigma: vec![0.0; dim],
+            p_c: vec![0.0; dim],
+        }
+    }
+
+    /// lambda 個の候補（[0, 1] に切り詰め済み）
+    pub fn ask<R: Rng>(&self, rng: &mut R) -> Vec<Vec<f64>> {
+        (0..self.lambda)
+            .map(|_| {
+                self.mean
+                    .iter()
+                    .zip(self.diag.iter())
+                    .map(|(m, c)| {
+                        (m + self.sigma * c.sqrt() * standard_normal(rng)).clamp(0.0, 1.0)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// ask で得た候補とその評価値（小さいほど良い）
+    #[allow(clippy::needless_range_loop)]
+    pub fn tell(&mut self, mut evaluated: Vec<(Vec<f64>, f64)>) {
+        let n = self.mean.len();
+        evaluated.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        let ys = evaluated
+            .iter()
+            .take(self.mu)
+            .map(|(x, _)| {
+                x.iter()
+                    .zip(self.mean.iter())
+                    .map(|(x, m)| (x - m) / self.sigma)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let mut y_w = vec![0.0; n];
+        for (w, y) in self.weights.iter().zip(ys.iter()) {
+            for i in 0..n {
+                y_w[i] += w * y[i];
+            }
+        }
+        for i in 0..n {
+            self.mean[i] += self.sigma * y_w[i];
+        }
+
+        let k_sigma = (self.c_sigma * (2.0 - self.c_sigma) * self.mu_eff).sqrt();
+        for i in 0..n {
+            self.p_sigma[i] =
+                (1.0 - self.c_sigma) * self.p_sigma[i] + k_sigma * y_w[i] / self.diag[i].sqrt();
+        }
+        let norm_p_sigma = self.p_sigma.iter().map(|p| p * p).sum::<f64>().sqrt();
+        self.generation += 1;
+        let h_sigma = norm_p_sigma
+            / (1.0 - (1.0 - self.c_sigma).powi(2 * self.generation as i32)).sqrt()
+            < (1.4 + 2.0 / (n as f64 + 1.0)) * self.chi_n;
+        let k_c = if h_sigma {
+            (self.c_c * (2.0 - self.c_c) * self.mu_eff).sqrt()
+        } else {
+            0.0
+        };
+        for i in 0..n {
+            self.p_c[i] = (1.0 - self.c_c) * self.p_c[i] + k_c * y_w[i];
+            let rank_mu: f64 = self
+                .weights
+                .iter()
+                .zip(ys.iter())
+                .map(|(w, y)| w * y[i] * y[i])
+                .sum();
+            self.diag[i] = (1.0 - self.c_1 - self.c_mu) * self.diag[i]
+                + self.c_1 * self.p_c[i] * self.p_c[i]
+                + self.c_mu * rank_mu;
+        }
+        self.sigma *= ((self.c_sigma / self.d_sigma) * (norm_p_sigma / self.chi_n - 1.0)).exp();
+    }
+}
+
+pub struct TuneConfig {
+    pub space: Vec<Dim>,
+    pub trials: usize,
+    /// 最初にランダムに試す数（1つ目は DEFAULT_PARAMS）
+    pub init: usize,
+    pub seed: u64,
+    pub sigma: f64,
+    /// 1回の実行のミリ秒
+    pub limit: u64,
+    /// Some なら時間の代わりに提案回数で打ち切る
+    pub iterations: Option<u64>,
+    pub threads: usize,
+}
+
+fn params_from(overrides: &Map<String, Value>) -> Result<McParams, String> {
+    serde_json::from_value(Value::Object(overrides.clone())).map_err(|e| e.to_string())
+}
+
+fn run_trial(id: usize, x: Vec<f64>, instances: &[Instance], config: &TuneConfig) -> Trial {
+    let overrides = decode(&config.space, &x);
+    let (score, scores, error) = match params_from(&overrides) {
+        Ok(params) => {
+            let run_config = RunConfig::with_params(params, 1, config.limit, config.iterations);
+            let scores = evaluate(instances, &run_config, config.threads);
+            let mean = scores.iter().sum::<f64>() / scores.len() as f64;
+            let scores = instances
+                .iter()
+                .map(|i| i.name.clone())
+                .zip(scores)
+                .collect();
+            (mean, scores, None)
+        }
+        Err(e) => (0.0, BTreeMap::new(), Some(e)),
+    };
+    Trial {
+        id,
+        x,
+        params: overrides,
+        score,
+        scores,
+        error,
+    }
+}
+
+fn trial_rng(seed: u64, stream: u64, index: u64) -> Mcg128Xsl64 {
+    Mcg128Xsl64::new(((seed as u128) << 64) | ((stream as u128) << 48) | index as u128)
+}
+
+fn same_x(a: &[f64], b: &[f64]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-12)
+}
+
+/// 既存の試行を同じ乱数で再生してから続きを探索する
+/// 設定を変えて再開すると再生した候補が記録と一致しなくなるのでエラーにする
+pub fn tune(study: &mut Study, instances: &[Instance], config: &TuneConfig) -> Result<()> {
+    let mut report = |trial: &Trial, best: f64| {
+        eprintln!(
+            "trial {:4}: {:.6} (best {:.6}){}",
+            trial.id,
+            trial.score,
+            best,
+            trial
+                .error
+                .as_ref()
+                .map_or(String::new(), |e| format!(" {}", e))
+        );
+    };
+
+    let dim = config.space.len();
+    for (i, trial) in study.trials.iter().enumerate() {
+        if trial.id != i || trial.x.len() != dim {
+            bail!("trial {} does not match the search space", i);
+        }
+    }
+
+    // ランダム探索
+    for id in 0..config.init.min(config.trials) {
+        let x = if id == 0 {
+            default_x(&config.space)
+        } else {
+            let mut rng = trial_rng(config.seed, 0, id as u64);
+            (0..dim).map(|_| rng.gen::<f64>()).collect()
+        };
+        evaluate_or_replay(study, id, x, instances, config, &mut report)?;
+    }
+
+    // CMA-ES
+    let mean = match study
+        .trials
+        .iter()
+        .take(config.init)
+        .max_by(|a, b| a.score.partial_cmp(&b.score).unwrap())
+    {
+        Some(best) => best.x.clone(),
+        None => default_x(&config.space),
+    };
+    let mut es = SepCmaEs::new(mean, config.sigma);
+    let mut generation = 0;
+    let mut id = config.init;
+    while id < config.trials {
+        let mut rng = trial_rng(config.seed, 1, generation);
+        let mut evaluated = Vec::with_capacity(es.lambda);
+        for x in es.ask(&mut rng) {
+            if id >= config.trials {
+                return Ok(());
+            }
+            let score = evaluate_or_replay(study, id, x.clone(), instances, config, &mut report)?;
+            evaluated.push((x, -score));
+            id += 1;
+        }
+        es.tell(evaluated);
+        generation += 1;
+    }
+    Ok(())
+}
+
+fn evaluate_or_replay<F: FnMut(&Trial, f64)>(
+    study: &mut Study,
+    id: usize,
+    x: Vec<f64>,
+    instances: &[Instance],
+    config: &TuneConfig,
+    report: &mut F,
+) -> Result<f64> {
+    if let Some(trial) = study.trials.get(id) {
+        if !same_x(&trial.x, &x) {
+            bail!(
+                "trial {} in the study was made with different settings (seed, init, sigma or space)",
+                id
+            );
+        }
+        return Ok(trial.score);
+    }
+    let trial = run_trial(id, x, instances, config);
+    let score = trial.score;
+    study.push(trial)?;
+    let best = study.best().map_or(score, |t| t.score);
+    report(study.trials.last().unwrap(), best);
+    Ok(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dim_roundtrip() {
+        let dim = Dim::new("grow_d1_start", 1.0, 1024.0, true);
+        assert!((dim.decode(0.5) - 32.0).abs() < 1e-9);
+        assert!((dim.encode(32.0) - 0.5).abs() < 1e-9);
+        let x = default_x(&default_space());
+        assert!((default_space()[4].decode(x[4]) - 661.4780032749206).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sep_cma_es_sphere() {
+        let target = [0.2, 0.7, 0.4, 0.9];
+        let mut es = SepCmaEs::new(vec![0.5; 4], 0.3);
+        let mut rng = Mcg128Xsl64::new(1);
+        for _ in 0..200 {
+            let evaluated = es
+                .ask(&mut rng)
+                .into_iter()
+                .map(|x| {
+                    let f = x
+                        .iter()
+                        .zip(target.iter())
+                        .map(|(a, b)| (a - b).powi(2))
+                        .sum();
+                    (x, f)
+                })
+                .collect();
+            es.tell(evaluated);
+        }
+        for (m, t) in es.mean.iter().zip(target.iter()) {
+            assert!((m - t).abs() < 1e-3);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 #![allow(non_snake_case)]
+#![allow(clippy::ptr_arg, clippy::needless_range_loop, clippy::iter_nth_zero)]
 
 use proconio::{input, source::*};
 